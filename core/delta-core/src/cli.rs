@@ -0,0 +1,482 @@
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use serde_json::Value as Json;
+use delta_lib::{ DeltaError, LogLevel, NodeRegistry, PortKind, Value };
+use crate::graph::{ GraphError, NodeId };
+use crate::log::StderrSink;
+use crate::program::{ value_to_json, LoadedProgram, Program, ProgramError };
+
+// the `delta` command line, `main` only loads the plugins and hands everything else to `execute`
+pub const USAGE: &str = "\
+usage:
+    delta run <program.json> [--set <node>.<input>=<value>]... [--format json|table] [--ticks <n>]
+    delta validate <program.json> [--set <node>.<input>=<value>]...
+    delta list-nodes
+
+options:
+    --set       give an input a value, replacing the one in the program. The value is JSON, anything
+                that is not valid JSON is taken as a string. Inputs of the program itself are set by name: --set a=1
+    --format    how `run` prints the outputs, a table (the default) or a JSON object
+    --ticks     run the program as a clock for at most this many ticks, instead of running every node once
+
+exit codes:
+    0   the program ran (or is valid)
+    1   a node failed while running
+    2   the command line is wrong
+    3   the program can not be loaded or can not run
+    4   the results could not be printed";
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_NODE_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_INVALID_PROGRAM: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+// `--set node.input=value`, `node` is None for the inputs of the program itself.
+// split on the first '.', the program's inputs are still looked up by the whole name in case theirs has a '.' in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub node: Option<String>,
+    pub input: String,
+    pub value: Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run { program: PathBuf, bindings: Vec<Binding>, format: OutputFormat, ticks: Option<u64> },
+    Validate { program: PathBuf, bindings: Vec<Binding> },
+    ListNodes,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+fn usage_error<T>(message: String) -> Result<T, UsageError> {
+    Err(UsageError(message))
+}
+
+// the arguments after the name of the binary
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, UsageError> {
+    let mut args = args.into_iter();
+    let command = match args.next() {
+        Some(command) => command,
+        None => return usage_error("no command given".to_owned()),
+    };
+
+    let mut program = None;
+    let mut bindings = vec![];
+    let mut format = None;
+    let mut ticks = None;
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| args.next().ok_or_else(|| UsageError(format!("{} needs a value", option)));
+        match arg.as_str() {
+            "--set" => bindings.push(parse_binding(&value("--set")?)?),
+            "--format" => format = Some(match value("--format")?.as_str() {
+                "table" => OutputFormat::Table,
+                "json" => OutputFormat::Json,
+                other => return usage_error(format!("unknown format `{}`, expected `json` or `table`", other)),
+            }),
+            "--ticks" => {
+                let n = value("--ticks")?;
+                ticks = Some(n.parse::<u64>().or_else(|_| usage_error(format!("--ticks expects a number, found `{}`", n)))?);
+            },
+            option if option.starts_with("--") => return usage_error(format!("unknown option `{}`", option)),
+            _ if program.is_none() => program = Some(PathBuf::from(arg)),
+            _ => return usage_error(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    // only `run` takes every option, anything given to a command that does not use it is a mistake
+    let unused = |options: &[(&str, bool)]| match options.iter().find(|(_, given)| *given) {
+        Some((option, _)) => usage_error(format!("`{}` does not take {}", command, option)),
+        None => Ok(()),
+    };
+    let needs_program = |program: Option<PathBuf>| program.ok_or_else(|| UsageError(format!("`{}` needs a program", command)));
+    match command.as_str() {
+        "run" => Ok(Command::Run { program: needs_program(program)?, bindings, format: format.unwrap_or(OutputFormat::Table), ticks }),
+        "validate" => {
+            unused(&[("--format", format.is_some()), ("--ticks", ticks.is_some())])?;
+            Ok(Command::Validate { program: needs_program(program)?, bindings })
+        },
+        "list-nodes" => {
+            unused(&[("a program", program.is_some()), ("--set", !bindings.is_empty()), ("--format", format.is_some()), ("--ticks", ticks.is_some())])?;
+            Ok(Command::ListNodes)
+        },
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => usage_error(format!("unknown command `{}`", command)),
+    }
+}
+
+fn parse_binding(arg: &str) -> Result<Binding, UsageError> {
+    let (target, value) = match arg.split_once('=') {
+        Some(split) => split,
+        None => return usage_error(format!("expected <node>.<input>=<value>, found `{}`", arg)),
+    };
+    let (node, input) = match target.split_once('.') {
+        Some((node, input)) => (Some(node.to_owned()), input),
+        None => (None, target),
+    };
+    if input.is_empty() || node.as_deref() == Some("") {
+        return usage_error(format!("expected <node>.<input>=<value>, found `{}`", arg));
+    }
+
+    // `--set name=bob` should not need quotes around quotes
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Json::String(value.to_owned()));
+    Ok(Binding { node, input: input.to_owned(), value })
+}
+
+// every node pack that comes with delta
+pub fn register_builtin(registry: &mut NodeRegistry) {
+    arithmetic::__delta_plugin_register(registry);
+    logic::__delta_plugin_register(registry);
+}
+
+// runs the command, printing results to `out` and errors to stderr. Returns the exit code
+pub fn execute(command: &Command, registry: &NodeRegistry, out: &mut dyn Write) -> i32 {
+    let result = match command {
+        Command::Run { program, bindings, format, ticks } => run(program, bindings, *format, *ticks, registry, out),
+        Command::Validate { program, bindings } => validate(program, bindings, registry, out),
+        Command::ListNodes => list_nodes(registry, out).map(|_| EXIT_OK),
+        Command::Help => writeln!(out, "{}", USAGE).map(|_| EXIT_OK),
+    };
+
+    // the only errors left are from writing to `out`
+    result.unwrap_or_else(|error| {
+        eprintln!("error: {}", error);
+        EXIT_IO
+    })
+}
+
+// builds the program with the bindings applied, prints why if it can not
+fn load(path: &Path, bindings: &[Binding], registry: &NodeRegistry) -> Option<LoadedProgram> {
+    let loaded = std::fs::read_to_string(path).map_err(ProgramError::Io)
+        .and_then(|json| Program::from_json(&json))
+        .map_err(|error| error.to_string())
+        .and_then(|mut program| {
+            bindings.iter().try_for_each(|binding| bind(&mut program, binding))?;
+            program.build(registry).map_err(|error| error.to_string())
+        });
+
+    match loaded {
+        Ok(loaded) => Some(loaded),
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            None
+        },
+    }
+}
+
+// bindings replace the input in the program, so building it checks them like any other input
+fn bind(program: &mut Program, binding: &Binding) -> Result<(), String> {
+    let name = match &binding.node {
+        Some(node) => format!("{}.{}", node, binding.input),
+        None => binding.input.clone(),
+    };
+    let (node, input) = match (program.inputs.iter().find(|port| port.name == name), &binding.node) {
+        (Some(port), _) => (port.node.clone(), port.port.clone()),
+        (None, Some(node)) => (node.clone(), binding.input.clone()),
+        (None, None) => return Err(format!("the program has no input named `{}`", binding.input)),
+    };
+
+    match program.nodes.iter_mut().find(|n| n.id == node) {
+        Some(node) => {
+            node.inputs.insert(input, binding.value.clone());
+            Ok(())
+        },
+        None => Err(ProgramError::UnknownNode(node).to_string()),
+    }
+}
+
+fn node_name(loaded: &LoadedProgram, node: NodeId) -> String {
+    loaded.nodes.iter().find(|(_, id)| **id == node).map_or_else(|| node.to_string(), |(name, _)| name.clone())
+}
+
+// graph errors name nodes by their index, which means nothing to whoever wrote the program
+fn describe(loaded: &LoadedProgram, error: &GraphError) -> String {
+    match error {
+        GraphError::DanglingInput { node, input } => format!("input `{}.{}` is not set or connected to anything", node_name(loaded, *node), input),
+        GraphError::Cycle(nodes) => format!("the nodes {} form a cycle",
+            nodes.iter().map(|node| format!("`{}`", node_name(loaded, *node))).collect::<Vec<_>>().join(", ")),
+        GraphError::NodeFailed { node, error } => {
+            let error = DeltaError { node: None, ..error.clone() };
+            format!("node `{}` failed: {}", node_name(loaded, *node), error)
+        },
+        error => error.to_string(),
+    }
+}
+
+fn run(path: &Path, bindings: &[Binding], format: OutputFormat, ticks: Option<u64>, registry: &NodeRegistry, out: &mut dyn Write)
+    -> std::io::Result<i32> {
+    let mut loaded = match load(path, bindings, registry) {
+        Some(loaded) => loaded,
+        None => return Ok(EXIT_INVALID_PROGRAM),
+    };
+    loaded.graph.set_log_sink(Box::new(StderrSink::new(LogLevel::Info)));
+
+    let result = match ticks {
+        Some(ticks) => loaded.graph.run_clocked(ticks).map(|_| ()),
+        None => loaded.graph.run(),
+    };
+    let code = match result {
+        Ok(()) if loaded.graph.errors().is_empty() => EXIT_OK,
+        Ok(()) => {
+            // the error policy let the run carry on, it still failed
+            for error in loaded.graph.errors() {
+                eprintln!("error: {}", error);
+            }
+            EXIT_NODE_FAILED
+        },
+        Err(error @ GraphError::NodeFailed { .. }) => {
+            eprintln!("error: {}", describe(&loaded, &error));
+            EXIT_NODE_FAILED
+        },
+        Err(error) => {
+            eprintln!("error: {}", describe(&loaded, &error));
+            return Ok(EXIT_INVALID_PROGRAM);
+        },
+    };
+
+    // whatever did run is still printed when a node failed
+    let outputs = outputs(&loaded);
+    match format {
+        OutputFormat::Json => {
+            let object: serde_json::Map<String, Json> = outputs.into_iter().map(|(name, _, value)| (name, value_to_json(value).unwrap_or(Json::Null))).collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&Json::Object(object)).unwrap_or_default())?;
+        },
+        OutputFormat::Table => {
+            let rows: Vec<[String; 3]> = outputs.into_iter().map(|(name, ty, value)| [name, ty.to_owned(), display(value)]).collect();
+            table(["output", "type", "value"], &rows, out)?;
+        },
+    }
+    Ok(code)
+}
+
+// what was sent during the run: the outputs of the program if it has any, otherwise every output of every node, as `node.output`
+fn outputs(loaded: &LoadedProgram) -> Vec<(String, &'static str, &Value)> {
+    let graph = &loaded.graph;
+    let ty = |node: NodeId, output: &str| graph.ports(node).ok()
+        .and_then(|ports| ports.iter().find(|p| p.kind == PortKind::Output && p.name == output))
+        .map_or("?", |p| p.ty);
+
+    if !loaded.outputs.is_empty() {
+        return loaded.outputs.iter().filter_map(|port| {
+            let node = *loaded.nodes.get(&port.node)?;
+            graph.output_value(node, &port.port).map(|value| (port.name.clone(), ty(node, &port.port), value))
+        }).collect();
+    }
+
+    loaded.nodes.iter().flat_map(|(name, &node)| {
+        graph.ports(node).unwrap_or(&[]).iter().filter(|p| p.kind == PortKind::Output).filter_map(move |p| {
+            graph.output_value(node, &p.name).map(|value| (format!("{}.{}", name, p.name), p.ty, value))
+        })
+    }).collect()
+}
+
+fn display(value: &Value) -> String {
+    match value_to_json(value) {
+        Some(Json::String(x)) => x,
+        Some(json) => json.to_string(),
+        None => format!("<{}>", value.kind()),
+    }
+}
+
+fn table<const N: usize>(header: [&str; N], rows: &[[String; N]], out: &mut dyn Write) -> std::io::Result<()> {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells.iter().zip(widths.iter()).map(|(cell, width)| format!("{:<1$}", cell, width)).collect();
+        writeln!(out, "{}", padded.join("  ").trim_end())
+    };
+    line(header.to_vec())?;
+    for row in rows {
+        line(row.iter().map(String::as_str).collect())?;
+    }
+    Ok(())
+}
+
+fn validate(path: &Path, bindings: &[Binding], registry: &NodeRegistry, out: &mut dyn Write) -> std::io::Result<i32> {
+    let loaded = match load(path, bindings, registry) {
+        Some(loaded) => loaded,
+        None => return Ok(EXIT_INVALID_PROGRAM),
+    };
+
+    match loaded.graph.validate() {
+        Ok(order) => {
+            let order: Vec<String> = order.into_iter().map(|node| node_name(&loaded, node)).collect();
+            writeln!(out, "{}: ok, {} nodes, run in the order {}", path.display(), order.len(), order.join(", "))?;
+            Ok(EXIT_OK)
+        },
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), describe(&loaded, &error));
+            Ok(EXIT_INVALID_PROGRAM)
+        },
+    }
+}
+
+fn list_nodes(registry: &NodeRegistry, out: &mut dyn Write) -> std::io::Result<()> {
+    for node in registry.nodes() {
+        writeln!(out, "{}", node.name)?;
+        if let Some(doc) = node.schema.doc.lines().next().filter(|doc| !doc.is_empty()) {
+            writeln!(out, "    {}", doc)?;
+        }
+        for input in node.schema.inputs.iter() {
+            let default = match (input.required, input.default) {
+                (true, _) => " (required)".to_owned(),
+                (false, Some(default)) => format!(" = {}", default),
+                (false, None) => String::new(),
+            };
+            writeln!(out, "    in  {}: {}{}", input.name, input.ty, default)?;
+        }
+        for output in node.schema.outputs.iter() {
+            writeln!(out, "    out {}: {}", output.name, output.ty)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use serde_json::json;
+    use delta_lib::NodeRegistry;
+    use crate::cli::{ execute, parse_args, register_builtin, Binding, Command, OutputFormat, EXIT_INVALID_PROGRAM, EXIT_IO, EXIT_NODE_FAILED, EXIT_OK };
+
+    fn args(line: &str) -> Result<Command, String> {
+        parse_args(line.split_whitespace().map(str::to_owned)).map_err(|error| error.to_string())
+    }
+
+    fn registry() -> NodeRegistry {
+        let mut registry = NodeRegistry::new();
+        register_builtin(&mut registry);
+        registry
+    }
+
+    // runs a command on a program written to a temporary file
+    fn execute_program(program: &str, line: &str) -> (i32, String) {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("delta-cli-{}-{}.json", std::process::id(), FILES.fetch_add(1, Ordering::SeqCst)));
+        std::fs::write(&path, program).unwrap();
+        let command = args(&line.replace("PROGRAM", path.to_str().unwrap())).unwrap();
+
+        let mut out = vec![];
+        let code = execute(&command, &registry(), &mut out);
+        std::fs::remove_file(&path).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    const DIVIDE: &str = r#"{
+        "format": "delta-program",
+        "version": 2,
+        "nodes": [
+            { "id": "half", "type": "arithmetic::numeric::Divi32", "inputs": { "x": 10, "y": 2 } },
+            { "id": "again", "type": "arithmetic::numeric::Divi32", "inputs": { "y": 5 } }
+        ],
+        "edges": [{ "from": "half", "output": "out", "to": "again", "input": "x" }],
+        "inputs": [{ "name": "divisor", "node": "half", "port": "y" }]
+    }"#;
+
+    #[test]
+    fn arguments() {
+        assert_eq!(Ok(Command::Run {
+            program: "p.json".into(),
+            bindings: vec![
+                Binding { node: Some("a".to_owned()), input: "x".to_owned(), value: json!(1) },
+                Binding { node: None, input: "name".to_owned(), value: json!("bob") },
+                Binding { node: Some("b".to_owned()), input: "list".to_owned(), value: json!([1, "x"]) },
+            ],
+            format: OutputFormat::Json,
+            ticks: Some(10),
+        }), args(r#"run p.json --set a.x=1 --set name=bob --format json --set b.list=[1,"x"] --ticks 10"#));
+        assert_eq!(Ok(Command::Validate { program: "p.json".into(), bindings: vec![] }), args("validate p.json"));
+        assert_eq!(Ok(Command::ListNodes), args("list-nodes"));
+
+        assert_eq!(Err("no command given".to_owned()), args(""));
+        assert_eq!(Err("`run` needs a program".to_owned()), args("run --format json"));
+        assert_eq!(Err("--set needs a value".to_owned()), args("run p.json --set"));
+        assert_eq!(Err("expected <node>.<input>=<value>, found `a.x`".to_owned()), args("run p.json --set a.x"));
+        assert_eq!(Err("unknown format `yaml`, expected `json` or `table`".to_owned()), args("run p.json --format yaml"));
+        assert_eq!(Err("`validate` does not take --ticks".to_owned()), args("validate p.json --ticks 2"));
+        assert_eq!(Err("unexpected argument `q.json`".to_owned()), args("run p.json q.json"));
+        assert_eq!(Err("unknown command `walk`".to_owned()), args("walk"));
+    }
+
+    #[test]
+    fn run() {
+        let (code, out) = execute_program(DIVIDE, "run PROGRAM");
+        assert_eq!(EXIT_OK, code);
+        assert_eq!("output     type  value\nagain.out  i32   1\nhalf.out   i32   5\n", out);
+
+        let (code, out) = execute_program(DIVIDE, "run PROGRAM --set divisor=5 --set again.y=-1 --format json");
+        assert_eq!(EXIT_OK, code);
+        assert_eq!(json!({ "half.out": 2, "again.out": -2 }), serde_json::from_str::<serde_json::Value>(&out).unwrap());
+
+        // the outputs that were sent before the failure are still printed
+        let (code, out) = execute_program(DIVIDE, "run PROGRAM --set again.y=0 --format json");
+        assert_eq!(EXIT_NODE_FAILED, code);
+        assert_eq!(json!({ "half.out": 5 }), serde_json::from_str::<serde_json::Value>(&out).unwrap());
+
+        assert_eq!(EXIT_INVALID_PROGRAM, execute_program(DIVIDE, "run PROGRAM --set half.y=two").0);
+        assert_eq!(EXIT_INVALID_PROGRAM, execute_program(DIVIDE, "run PROGRAM --set missing.y=1").0);
+        assert_eq!(EXIT_INVALID_PROGRAM, execute_program(DIVIDE, "run PROGRAM --set dividend=1").0);
+
+        // a program input with a '.' in its name is not taken for a node
+        let dotted = DIVIDE.replace(r#""name": "divisor""#, r#""name": "half.divisor""#);
+        let (code, out) = execute_program(&dotted, "run PROGRAM --set half.divisor=5 --format json");
+        assert_eq!(EXIT_OK, code);
+        assert_eq!(json!({ "half.out": 2, "again.out": 0 }), serde_json::from_str::<serde_json::Value>(&out).unwrap());
+        assert_eq!(EXIT_INVALID_PROGRAM, execute_program("{}", "run PROGRAM").0);
+    }
+
+    #[test]
+    fn validate() {
+        let (code, out) = execute_program(DIVIDE, "validate PROGRAM");
+        assert_eq!(EXIT_OK, code);
+        assert!(out.ends_with(": ok, 2 nodes, run in the order half, again\n"));
+
+        let dangling = DIVIDE.replace(r#""inputs": { "x": 10, "y": 2 }"#, r#""inputs": { "y": 2 }"#);
+        assert_eq!((EXIT_INVALID_PROGRAM, String::new()), execute_program(&dangling, "validate PROGRAM"));
+        assert_eq!(EXIT_OK, execute_program(&dangling, "validate PROGRAM --set half.x=1").0);
+    }
+
+    #[test]
+    fn list_nodes() {
+        let mut out = vec![];
+        assert_eq!(EXIT_OK, execute(&Command::ListNodes, &registry(), &mut out));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("arithmetic::numeric::Divi32\n    Division, a divisor of zero is an error.\n    in  x: i32 (required)\n"));
+        assert!(out.contains("logic::And\n"));
+
+        // like stdout piped into something that already exited
+        struct Closed;
+        impl std::io::Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        assert_eq!(EXIT_IO, execute(&Command::ListNodes, &registry(), &mut Closed));
+    }
+}
@@ -0,0 +1,435 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{ BTreeMap, BTreeSet };
+use std::sync::{ Arc, Mutex };
+use delta_lib::{ DeltaFuture, DeltaMessage, DynDeltaNode, FieldSchema, Impulse, NodeRegistry, NodeSchema, PortDescriptor, PortError,
+    PortKind, RegistryError, SendNode, SetError, Value };
+use crate::graph::{ Graph, GraphError, NodeId };
+use crate::log::ForwardSink;
+use crate::program::{ LoadedProgram, Program, ProgramError, ProgramMetadata, ProgramPort };
+
+// an inner port that is also a port of the composite
+struct Exposed {
+    field: FieldSchema, // the schema of the inner port, under the name it is exposed as
+    node: NodeId,
+    port: Cow<'static, str>, // the name of the inner port
+    index: usize, // the index of the inner port, for inputs
+    dynamic: bool, // the inner port carries a `Value`
+    error: bool, // the inner input takes a `DeltaError`
+}
+
+// a node made out of a whole graph, some of the inner ports are the ports of the composite.
+// every execution runs the inner graph once (`Graph::run`). Exposed inputs are reset after each execution like any other
+// input, and an exposed output sends whatever the inner node sent on it, NOOP if it did not run.
+// composites can be built in code with `expose_input` and `expose_output`, from a program with `inputs` and `outputs`,
+// or registered as a node type with `register_composite`
+pub struct Composite {
+    ty: String,
+    graph: Graph,
+    names: BTreeMap<String, NodeId>, // the ids of the inner nodes, for saving the composite as a program
+    metadata: ProgramMetadata,
+    inputs: Vec<Exposed>,
+    outputs: Vec<Exposed>,
+    set: Vec<bool>, // which inputs have been set since the last execution
+    logs: Arc<Mutex<Vec<DeltaMessage>>>,
+}
+
+impl Composite {
+    // `ty` is the name of the node type, e.g. `mylib::AddThree`
+    pub fn new(ty: &str, mut graph: Graph) -> Composite {
+        let logs = Arc::new(Mutex::new(vec![]));
+        graph.set_log_sink(Box::new(ForwardSink::new(logs.clone())));
+
+        Composite {
+            ty: ty.to_owned(),
+            graph,
+            names: BTreeMap::new(),
+            metadata: ProgramMetadata::default(),
+            inputs: vec![],
+            outputs: vec![],
+            set: vec![],
+            logs,
+        }
+    }
+
+    // a loaded program as a composite, its `inputs` and `outputs` become the ports of the composite
+    pub fn from_program(ty: &str, program: LoadedProgram) -> Result<Composite, ProgramError> {
+        let LoadedProgram { graph, nodes, metadata, inputs, outputs } = program;
+        let mut composite = Composite::new(ty, graph);
+        composite.metadata = metadata;
+
+        let mut names = BTreeSet::new();
+        for (port, kind) in inputs.iter().map(|p| (p, PortKind::Input)).chain(outputs.iter().map(|p| (p, PortKind::Output))) {
+            if !names.insert((&port.name, kind == PortKind::Input)) {
+                return Err(ProgramError::DuplicatePort(port.name.clone()));
+            }
+            let node = *nodes.get(&port.node).ok_or_else(|| ProgramError::UnknownNode(port.node.clone()))?;
+            let exposed = match kind {
+                PortKind::Input => composite.expose_input(&port.name, node, &port.port),
+                PortKind::Output => composite.expose_output(&port.name, node, &port.port),
+            };
+            exposed.map_err(|error| ProgramError::Graph { node: port.node.clone(), error })?;
+        }
+
+        composite.names = nodes;
+        Ok(composite)
+    }
+
+    // make an inner input an input of the composite. Exposing a name again replaces the input it was exposed for,
+    // but an inner input can only be exposed under one name
+    pub fn expose_input(&mut self, name: &str, node: NodeId, input: &str) -> Result<(), GraphError> {
+        let index = self.graph.free_input(node, input)?;
+        let (field, inner) = self.inner_field(node, input, PortKind::Input)?;
+        if self.inputs.iter().any(|e| e.node == node && e.index == index && e.field.name != name) {
+            return Err(GraphError::InputAlreadyConnected { node, input: input.to_owned() });
+        }
+        self.inputs.retain(|e| e.field.name != name);
+        self.inputs.push(Exposed { port: field.name.clone(), field: FieldSchema { name: Cow::Owned(name.to_owned()), ..field }, node, index, dynamic: inner.dynamic, error: inner.error });
+        self.set = vec![false; self.inputs.len()];
+        Ok(())
+    }
+
+    // make an inner output an output of the composite. An output can be exposed any number of times
+    pub fn expose_output(&mut self, name: &str, node: NodeId, output: &str) -> Result<(), GraphError> {
+        self.graph.check_output(node, output)?;
+        let (field, inner) = self.inner_field(node, output, PortKind::Output)?;
+        self.outputs.retain(|e| e.field.name != name);
+        self.outputs.push(Exposed { port: field.name.clone(), field: FieldSchema { name: Cow::Owned(name.to_owned()), ..field }, node, index: 0, dynamic: inner.dynamic, error: false });
+        Ok(())
+    }
+
+    // the schema of an inner port along with its descriptor
+    fn inner_field(&self, node: NodeId, port: &str, kind: PortKind) -> Result<(FieldSchema, PortDescriptor), GraphError> {
+        let schema = self.graph.schema(node)?;
+        let field = match kind {
+            PortKind::Input => schema.input(port),
+            PortKind::Output => schema.output(port),
+        };
+        let field = field.cloned().ok_or_else(|| GraphError::UnknownPort { node, port: port.to_owned() })?;
+        let inner = self.graph.ports(node)?.iter().find(|p| p.kind == kind && p.name == field.name).cloned()
+            .ok_or_else(|| GraphError::UnknownPort { node, port: port.to_owned() })?;
+        Ok((field, inner))
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    // the composite as a program, for saving it along with the programs that use it
+    pub fn to_program(&self) -> Result<Program, ProgramError> {
+        let mut program = Program::from_graph(&self.graph, &self.names)?;
+        program.metadata = self.metadata.clone();
+
+        let ids: Vec<String> = program.nodes.iter().map(|n| n.id.clone()).collect();
+        let port = |e: &Exposed| ProgramPort { name: e.field.name.to_string(), node: ids[e.node.0].clone(), port: e.port.to_string() };
+        program.inputs = self.inputs.iter().map(port).collect();
+        program.outputs = self.outputs.iter().map(port).collect();
+
+        // inputs set on the composite since it last ran are not part of what it is
+        for input in self.inputs.iter() {
+            program.nodes[input.node.0].inputs.remove(&*input.port);
+        }
+        Ok(program)
+    }
+}
+
+// register a program as a node type, every node created from it is a `Composite` of a freshly built program.
+// the program is built once here to check it, node types it uses have to be registered first.
+// creating one fails if the program can no longer be built, e.g. because a node type it uses was replaced
+pub fn register_composite(registry: &mut NodeRegistry, ty: &str, program: Program) -> Result<(), ProgramError> {
+    let composite = Composite::from_program(ty, program.build(registry)?)?;
+    let (ports, schema) = (composite.__port_list(), composite.__describe_dyn());
+
+    let name = ty.to_owned();
+    registry.register(ty, ports, schema, Box::new(move |registry| -> Result<Box<dyn DynDeltaNode>, RegistryError> {
+        match program.build(registry).and_then(|loaded| Composite::from_program(&name, loaded)) {
+            Ok(composite) => Ok(Box::new(composite)),
+            Err(error) => Err(RegistryError::CreateFailed { node: name.clone(), error: error.to_string() }),
+        }
+    }));
+    Ok(())
+}
+
+impl DynDeltaNode for Composite {
+    fn __type_name(&self) -> &str {
+        &self.ty
+    }
+
+    fn __port_list(&self) -> Vec<PortDescriptor> {
+        let port = |(index, e): (usize, &Exposed), kind| PortDescriptor { name: e.field.name.clone(), ty: e.field.ty, index, kind, required: e.field.required, dynamic: e.dynamic, error: e.error };
+        self.inputs.iter().enumerate().map(|e| port(e, PortKind::Input))
+            .chain(self.outputs.iter().enumerate().map(|e| port(e, PortKind::Output)))
+            .collect()
+    }
+
+    fn __describe_dyn(&self) -> NodeSchema {
+        NodeSchema {
+            name: Cow::Owned(self.ty.clone()),
+            doc: Cow::Owned(self.metadata.description.clone()),
+            inputs: self.inputs.iter().map(|e| e.field.clone()).collect(),
+            outputs: self.outputs.iter().map(|e| e.field.clone()).collect(),
+            state: vec![],
+        }
+    }
+
+    fn __set_input(&mut self, index: usize, value: &Value) -> Result<(), PortError> {
+        let input = self.inputs.get(index).ok_or(PortError::UnknownPort(index))?;
+        self.graph.bind_checked(input.node, input.index, value.clone()).map_err(|error| match error {
+            PortError::TypeMismatch { expected, found, .. } => PortError::TypeMismatch { port: input.field.name.to_string(), expected, found },
+            error => error,
+        })?;
+        self.set[index] = true;
+        Ok(())
+    }
+
+    fn __unset_input(&mut self, index: usize) {
+        if let Some(input) = self.inputs.get(index) {
+            self.graph.unbind(input.node, input.index);
+            self.set[index] = false;
+        }
+    }
+
+    fn __set_by_name_dyn(&mut self, field: &str, value: Value) -> Result<(), SetError> {
+        let index = self.inputs.iter().position(|e| e.field.name == field).ok_or_else(|| SetError::UnknownField(field.to_owned()))?;
+        match self.__set_input(index, &value) {
+            Ok(()) => Ok(()),
+            Err(PortError::TypeMismatch { port, expected, .. }) => Err(SetError::TypeMismatch { field: port, expected, found: value }),
+            Err(PortError::UnknownPort(_)) => Err(SetError::UnknownField(field.to_owned())),
+        }
+    }
+
+    fn __ready(&self) -> bool {
+        self.inputs.iter().zip(self.set.iter()).all(|(e, set)| *set || !e.field.required)
+    }
+
+    // the inner graph keeps its own clock, it is not clocked
+    fn __set_clock(&mut self, _tick: u64) {}
+
+    fn __drain_logs(&mut self) -> Vec<DeltaMessage> {
+        std::mem::take(&mut *self.logs.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn __execute_dyn(&mut self) -> Vec<Impulse<Value>> {
+        let result = self.graph.run();
+        for input in self.inputs.iter() {
+            self.graph.unbind(input.node, input.index);
+        }
+        self.set = vec![false; self.inputs.len()];
+
+        let mut outputs: Vec<Impulse<Value>> = self.outputs.iter()
+            .map(|e| self.graph.impulse(e.node, &e.port).cloned().unwrap_or(Impulse::NOOP))
+            .collect();
+        // nodes that fail under a policy that does not halt the inner graph still fail the composite,
+        // otherwise their errors would never leave it
+        let error = match result {
+            Err(error) => Some(error.into_delta_error()),
+            Ok(()) => self.graph.errors().first().cloned(),
+        };
+        if let Some(error) = error {
+            // the error goes on the first output, where the graph looks for it
+            outputs = vec![Impulse::NOOP; outputs.len().max(1)];
+            outputs[0] = Impulse::ERROR(error);
+        }
+        outputs
+    }
+
+    fn __execute_dyn_async(&mut self) -> DeltaFuture<'_, Vec<Impulse<Value>>> {
+        Box::pin(async move { self.__execute_dyn() })
+    }
+
+    // the inner graph can hold thread local nodes
+    fn __as_send(&mut self) -> Option<&mut dyn SendNode> {
+        None
+    }
+
+    fn __as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn __as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use delta_lib::{ DeltaNode, DynDeltaNode, ErrorKind, Impulse, NodeRegistry, PortKind, RegistryError, SetError, Value };
+    use delta_lib_macro::{ delta_node_struct, delta_node_impl, RegisterDeltaNode };
+    use crate::composite::{ Composite, register_composite };
+    use crate::graph::{ ErrorPolicy, Graph, GraphError, NodeId };
+    use crate::program::{ Program, ProgramError };
+
+    /// Adds x and y.
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Add {
+        /// The first number
+        x: i32,
+        #[delta_default(0)]
+        y: i32,
+    }
+
+    #[delta_node_impl( on_exec = "add" )]
+    impl Add {
+        fn add(&mut self) -> Result<Impulse<i32>, DeltaError> {
+            self.x.checked_add(self.y).map(Impulse::SEND).ok_or_else(|| DeltaError::new(ErrorKind::Overflow, "too big"))
+        }
+    }
+
+    use delta_lib::DeltaError;
+
+    // a + b + c
+    fn add_three() -> Composite {
+        let mut graph = Graph::new();
+        let first = graph.add_node(Add::__initialize());
+        let second = graph.add_node(Add::__initialize());
+        graph.connect(first, "out", second, "x").unwrap();
+
+        let mut composite = Composite::new("tests::AddThree", graph);
+        composite.expose_input("a", first, "x").unwrap();
+        composite.expose_input("b", first, "y").unwrap();
+        composite.expose_input("c", second, "y").unwrap();
+        composite.expose_output("sum", second, "out").unwrap();
+        composite
+    }
+
+    const PROGRAM: &str = r#"{
+        "format": "delta-program",
+        "version": 2,
+        "composites": [
+            {
+                "type": "tests::AddThree",
+                "metadata": { "description": "Adds three numbers." },
+                "nodes": [
+                    { "id": "first", "type": "delta_core::composite::tests::Add" },
+                    { "id": "second", "type": "delta_core::composite::tests::Add" }
+                ],
+                "edges": [{ "from": "first", "output": "out", "to": "second", "input": "x" }],
+                "inputs": [
+                    { "name": "a", "node": "first", "port": "x" },
+                    { "name": "b", "node": "first", "port": "y" },
+                    { "name": "c", "node": "second", "port": "y" }
+                ],
+                "outputs": [{ "name": "sum", "node": "second", "port": "out" }]
+            }
+        ],
+        "nodes": [
+            { "id": "three", "type": "tests::AddThree", "inputs": { "a": 1, "b": 2, "c": 3 } },
+            { "id": "again", "type": "tests::AddThree", "inputs": { "b": 10 } }
+        ],
+        "edges": [{ "from": "three", "output": "sum", "to": "again", "input": "a" }]
+    }"#;
+
+    fn registry() -> NodeRegistry {
+        let mut registry = NodeRegistry::new();
+        Add::__register(&mut registry);
+        registry
+    }
+
+    #[test]
+    fn ports() {
+        let mut composite = add_three();
+        let ports = composite.__port_list();
+        let names: Vec<(&str, &str, bool)> = ports.iter().map(|p| (&*p.name, p.ty, p.required)).collect();
+        assert_eq!(vec![("a", "i32", true), ("b", "i32", false), ("c", "i32", false), ("sum", "i32", false)], names);
+        assert_eq!("The first number", composite.__describe_dyn().input("a").unwrap().doc);
+        assert_eq!("tests::AddThree", composite.__type_name());
+
+        assert!(!composite.__ready());
+        composite.__set_by_name_dyn("a", Value::Int(1)).unwrap();
+        assert!(composite.__ready());
+        assert_eq!(Err(SetError::TypeMismatch { field: "b".to_owned(), expected: "i32", found: Value::Float(0.5) }),
+            composite.__set_by_name_dyn("b", Value::Float(0.5)));
+        assert_eq!(Err(SetError::UnknownField("x".to_owned())), composite.__set_by_name_dyn("x", Value::Int(1)));
+
+        // an inner input can only be exposed if nothing inside sets it
+        let mut graph = Graph::new();
+        let add = graph.add_node(Add::__initialize());
+        graph.set_input(add, "x", 1).unwrap();
+        let mut composite = Composite::new("tests::Bound", graph);
+        assert!(matches!(composite.expose_input("x", add, "x"), Err(GraphError::InputAlreadyConnected { .. })));
+
+        // and only under one name, exposing the same name again is fine
+        let mut composite = add_three();
+        assert!(matches!(composite.expose_input("d", NodeId(0), "x"), Err(GraphError::InputAlreadyConnected { .. })));
+        composite.expose_input("a", NodeId(0), "x").unwrap();
+        assert_eq!(3, composite.__port_list().iter().filter(|p| p.kind == PortKind::Input).count());
+        assert!(matches!(composite.expose_output("x", add, "nope"), Err(GraphError::UnknownPort { .. })));
+    }
+
+    #[test]
+    fn in_a_graph() {
+        let mut graph = Graph::new();
+        let three = graph.add_node(Box::new(add_three()));
+        let after = graph.add_node(Add::__initialize());
+        graph.set_input(three, "a", 1).unwrap();
+        graph.set_input(three, "c", 3).unwrap();
+        graph.connect(three, "sum", after, "x").unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(4), graph.output::<i32>(three, "sum"));
+        assert_eq!(Some(4), graph.output::<i32>(after, "out"));
+
+        // an inner node failing fails the composite
+        let mut graph = Graph::new();
+        let three = graph.add_node(Box::new(add_three()));
+        graph.set_input(three, "a", i32::MAX).unwrap();
+        graph.set_input(three, "b", 1).unwrap();
+        assert!(matches!(graph.run(), Err(GraphError::NodeFailed { node, error }) if node == three && error.kind == ErrorKind::Overflow));
+
+        // even when the inner graph carries on past the failure
+        let mut composite = add_three();
+        composite.graph.set_error_policy(ErrorPolicy::SkipDownstream).unwrap();
+        let mut graph = Graph::new();
+        let three = graph.add_node(Box::new(composite));
+        graph.set_input(three, "a", i32::MAX).unwrap();
+        graph.set_input(three, "b", 1).unwrap();
+        assert!(matches!(graph.run(), Err(GraphError::NodeFailed { node, error }) if node == three && error.kind == ErrorKind::Overflow));
+    }
+
+    #[test]
+    fn programs() {
+        let program = Program::from_json(PROGRAM).unwrap();
+        let mut loaded = program.build(&registry()).unwrap();
+        loaded.graph.run().unwrap();
+        assert_eq!(Some(16), loaded.graph.output::<i32>(loaded.nodes["again"], "sum"));
+
+        // the composite is saved along with the program, the inputs set on it are not part of it
+        assert_eq!(program, loaded.to_program().unwrap());
+        assert_eq!("Adds three numbers.", loaded.graph.schema(loaded.nodes["three"]).unwrap().doc);
+
+        // a program with inputs and outputs can be registered as a node type
+        let mut registry = registry();
+        let definition = program.composites[0].program.clone();
+        register_composite(&mut registry, "tests::Registered", definition).unwrap();
+        assert_eq!(4, registry.get("tests::Registered").unwrap().ports.len());
+        let mut graph = Graph::new();
+        let node = graph.add_node(registry.create("tests::Registered").unwrap());
+        graph.set_input(node, "a", 5).unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(5), graph.output::<i32>(node, "sum"));
+
+        // once the program can not be built any more, creating the composite fails
+        let add = registry.get("delta_core::composite::tests::Add").unwrap();
+        let (ports, schema) = (add.ports.clone(), add.schema.clone());
+        registry.register("delta_core::composite::tests::Add", ports, schema, Box::new(|_| Err(RegistryError::UnknownNode("gone".to_owned()))));
+        assert!(matches!(registry.create("tests::Registered"), Err(RegistryError::CreateFailed { node, .. }) if node == "tests::Registered"));
+    }
+
+    #[test]
+    fn program_errors() {
+        let build = |composite: &str| {
+            let json = format!(r#"{{ "format": "delta-program", "version": 2, "composites": [{}], "nodes": [] }}"#, composite);
+            Program::from_json(&json).unwrap().build(&registry()).map(|_| ())
+        };
+
+        assert!(matches!(build(r#"{ "type": "A", "nodes": [{ "id": "a", "type": "A" }] }"#), Err(ProgramError::RecursiveComposite(ty)) if ty == "A"));
+        assert!(matches!(build(r#"{ "type": "A", "nodes": [], "outputs": [{ "name": "x", "node": "b", "port": "out" }] }"#),
+            Err(ProgramError::Composite { ty, error }) if ty == "A" && matches!(*error, ProgramError::UnknownNode(_))));
+        assert!(matches!(build(r#"{ "type": "A", "nodes": [{ "id": "a", "type": "delta_core::composite::tests::Add" }],
+            "inputs": [{ "name": "x", "node": "a", "port": "x" }, { "name": "x", "node": "a", "port": "y" }] }"#),
+            Err(ProgramError::Composite { error, .. }) if matches!(*error, ProgramError::DuplicatePort(_))));
+        assert!(matches!(build(r#"{ "type": "A", "nodes": [{ "id": "a", "type": "delta_core::composite::tests::Add" }],
+            "inputs": [{ "name": "x", "node": "a", "port": "z" }] }"#),
+            Err(ProgramError::Composite { error, .. }) if matches!(*error, ProgramError::Graph { .. })));
+    }
+}
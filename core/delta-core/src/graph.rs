@@ -0,0 +1,1634 @@
+use std::any::Any;
+use std::collections::{ HashMap, VecDeque };
+use std::sync::Mutex;
+use delta_lib::{ DeltaError, DeltaFuture, DeltaMessage, DynDeltaNode, ErrorKind, Impulse, LogLevel, NodeSchema, PortDescriptor, PortError, PortKind, SendNode,
+    Value, join_all };
+use crate::log::LogSink;
+
+// handle to a node inside of a graph, only valid for the graph that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// a connection from an output port of one node to an input port of another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: NodeId,
+    pub output: usize,
+    pub to: NodeId,
+    pub input: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    UnknownNode(NodeId),
+    UnknownPort { node: NodeId, port: String },
+    TypeMismatch { from: NodeId, output: String, to: NodeId, input: String, expected: &'static str, found: &'static str },
+    InputAlreadyConnected { node: NodeId, input: String },
+    DanglingInput { node: NodeId, input: String },
+    Cycle(Vec<NodeId>),
+    Port { node: NodeId, error: PortError },
+    NodeFailed { node: NodeId, error: DeltaError },
+}
+
+// what the graph does when a node sends an `Impulse::ERROR`.
+// every failure is logged at the Error level and recorded in `Graph::errors`, whatever the policy
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    #[default]
+    Halt,                                            // stop the run, it returns `GraphError::NodeFailed`
+    SkipDownstream,                                  // send nothing, so nothing downstream of the node runs, and carry on
+    Route { node: NodeId, input: usize },            // send the error to a `DeltaError` input of another node, see `Graph::route_errors`
+    Retry { attempts: u32, then: Box<ErrorPolicy> }, // run the node again with the same inputs, up to `attempts` more times
+}
+
+impl ErrorPolicy {
+    // the handler input errors end up at, if they are routed at all, including after retrying
+    fn route(&self) -> Option<(NodeId, usize)> {
+        match self {
+            ErrorPolicy::Route { node, input } => Some((*node, *input)),
+            ErrorPolicy::Retry { then, .. } => then.route(),
+            _ => None,
+        }
+    }
+}
+
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::UnknownNode(node) => write!(f, "node {} does not exist", node),
+            GraphError::UnknownPort { node, port } => write!(f, "node {} has no port `{}`", node, port),
+            GraphError::TypeMismatch { from, output, to, input, expected, found } =>
+                write!(f, "can not connect {}.{} to {}.{}, expected `{}` but found `{}`", from, output, to, input, expected, found),
+            GraphError::InputAlreadyConnected { node, input } => write!(f, "input {}.{} already has a value connected to it", node, input),
+            GraphError::DanglingInput { node, input } => write!(f, "input {}.{} is not connected to anything", node, input),
+            GraphError::Cycle(nodes) => {
+                let nodes: Vec<String> = nodes.iter().map(|n| n.to_string()).collect();
+                write!(f, "graph contains a cycle through nodes {}", nodes.join(", "))
+            },
+            GraphError::Port { node, error } => write!(f, "node {}: {}", node, error),
+            GraphError::NodeFailed { node, error } => write!(f, "node {} failed, {}", node, error),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl GraphError {
+    // for a node that runs a graph of its own (loops and composites), a failed inner node fails the outer one with the same error
+    pub(crate) fn into_delta_error(self) -> DeltaError {
+        match self {
+            GraphError::NodeFailed { error, .. } => error,
+            error => DeltaError::new(ErrorKind::Other, format!("the inner graph can not run, {}", error)),
+        }
+    }
+}
+
+// everything a node sent out during one execution, in output port order
+type NodeOutputs = Vec<Impulse<Value>>;
+
+// a dataflow graph of nodes.
+// every node runs at most once per `run`, once all of its required inputs are set.
+// each SEND on an output port is delivered to every input connected to it, a NOOP delivers nothing,
+// so anything downstream of a NOOP does not run.
+// in clocked mode (`run_clocked`) a node that returns TICK is run again on the next step
+#[derive(Default)]
+pub struct Graph {
+    nodes: Vec<Box<dyn DynDeltaNode>>,
+    ports: Vec<Vec<PortDescriptor>>, // cached port list of each node
+    edges: Vec<Edge>,
+    bindings: HashMap<(NodeId, usize), Value>, // inputs given a fixed value instead of an edge
+    outputs: Vec<Option<NodeOutputs>>, // what each node produced during the last run
+    tick: u64,
+    log_sink: Option<Box<dyn LogSink>>, // messages are dropped if there is nowhere to put them
+    error_policy: ErrorPolicy, // used for every node without its own policy
+    node_error_policies: HashMap<NodeId, ErrorPolicy>,
+    errors: Vec<DeltaError>, // every node failure during the last run
+    threads: usize, // worker threads for executing a level, 0 or 1 executes everything on the calling thread
+    levels: Option<Vec<Vec<NodeId>>>, // the nodes on each level, kept between runs until a node, edge or error route is added
+}
+
+impl Graph {
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+
+    // any node type can be added, e.g. `graph.add_node(Addi32::__initialize())`
+    pub fn add_node(&mut self, node: Box<dyn DynDeltaNode>) -> NodeId {
+        self.ports.push(node.__port_list());
+        self.nodes.push(node);
+        self.outputs.push(None);
+        self.levels = None;
+        NodeId(self.nodes.len() - 1)
+    }
+
+    // execute the nodes of each level on up to `threads` worker threads.
+    // nodes marked `#[delta_thread_local]` still run on the calling thread, next to the workers
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    pub fn set_log_sink(&mut self, sink: Box<dyn LogSink>) {
+        self.log_sink = Some(sink);
+    }
+
+    // the policy for every node that does not have one of its own, `ErrorPolicy::Halt` by default.
+    // routing sends the errors of all of those nodes to the same input, so the handler runs after every one of them.
+    // the handler can not take its own errors, it halts the run instead
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) -> Result<(), GraphError> {
+        // the policy that is replaced does not hold on to its handler input
+        let previous = std::mem::take(&mut self.error_policy);
+        if let Err(error) = self.check_route(None, &policy) {
+            self.error_policy = previous;
+            return Err(error);
+        }
+        self.error_policy = policy;
+        self.levels = None;
+        Ok(())
+    }
+
+    pub fn set_node_error_policy(&mut self, node: NodeId, policy: ErrorPolicy) -> Result<(), GraphError> {
+        self.ports(node)?;
+        let previous = self.node_error_policies.remove(&node);
+        if let Err(error) = self.check_route(Some(node), &policy) {
+            if let Some(previous) = previous {
+                self.node_error_policies.insert(node, previous);
+            }
+            return Err(error);
+        }
+        self.node_error_policies.insert(node, policy);
+        self.levels = None;
+        Ok(())
+    }
+
+    // send the errors of one node to an input of another, instead of its normal outputs
+    pub fn route_errors(&mut self, from: NodeId, to: NodeId, input: &str) -> Result<(), GraphError> {
+        let index = self.port(to, input, PortKind::Input)?.index;
+        self.set_node_error_policy(from, ErrorPolicy::Route { node: to, input: index })
+    }
+
+    // the errors nodes sent during the last run
+    pub fn errors(&self) -> &[DeltaError] {
+        &self.errors
+    }
+
+    // get a node back as its concrete type
+    pub fn node<N: 'static>(&self, node: NodeId) -> Option<&N> {
+        self.nodes.get(node.0)?.__as_any().downcast_ref::<N>()
+    }
+
+    pub fn node_mut<N: 'static>(&mut self, node: NodeId) -> Option<&mut N> {
+        self.nodes.get_mut(node.0)?.__as_any_mut().downcast_mut::<N>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn ports(&self, node: NodeId) -> Result<&[PortDescriptor], GraphError> {
+        match self.ports.get(node.0) {
+            Some(ports) => Ok(ports),
+            None => Err(GraphError::UnknownNode(node)),
+        }
+    }
+
+    // connect an output port to an input port, both ports have to carry the same type.
+    // a `Value` port on either side can be connected to anything, whether the value converts is only known once it is sent
+    pub fn connect(&mut self, from: NodeId, output: &str, to: NodeId, input: &str) -> Result<Edge, GraphError> {
+        let out_port = self.port(from, output, PortKind::Output)?.clone();
+        let in_port = self.port(to, input, PortKind::Input)?.clone();
+
+        if out_port.ty != in_port.ty && !out_port.dynamic && !in_port.dynamic {
+            return Err(GraphError::TypeMismatch {
+                from, output: out_port.name.into_owned(), to, input: in_port.name.into_owned(), expected: in_port.ty, found: out_port.ty,
+            });
+        }
+        self.check_unconnected(to, &in_port)?;
+
+        let edge = Edge { from, output: out_port.index, to, input: in_port.index };
+        self.edges.push(edge);
+        self.levels = None;
+        Ok(edge)
+    }
+
+    // give an input a fixed value, it is set again at the start of every run
+    pub fn set_input<T: Any + Send + Sync>(&mut self, node: NodeId, input: &str, value: T) -> Result<(), GraphError> {
+        self.set_input_value(node, input, Value::from_any(value))
+    }
+
+    // same as `set_input` for a value whose type is only known at runtime.
+    // the value is set on the node right away, so a value that does not convert to the type of the port is an error here
+    pub fn set_input_value(&mut self, node: NodeId, input: &str, value: Value) -> Result<(), GraphError> {
+        let in_port = self.port(node, input, PortKind::Input)?.clone();
+        self.check_unconnected(node, &in_port)?;
+        self.nodes[node.0].__set_input(in_port.index, &value).map_err(|error| GraphError::Port { node, error })?;
+        self.bindings.insert((node, in_port.index), value);
+        Ok(())
+    }
+
+    // every input given a fixed value, as (node, input port index, value)
+    pub fn bindings(&self) -> impl Iterator<Item = (NodeId, usize, &Value)> + '_ {
+        self.bindings.iter().map(|((node, input), value)| (*node, *input, value))
+    }
+
+    // the signature of a node, see `DynDeltaNode::__describe_dyn`
+    pub fn schema(&self, node: NodeId) -> Result<NodeSchema, GraphError> {
+        self.nodes.get(node.0).map(|n| n.__describe_dyn()).ok_or(GraphError::UnknownNode(node))
+    }
+
+    // the registered type name of a node
+    pub fn node_type(&self, node: NodeId) -> Result<&str, GraphError> {
+        self.nodes.get(node.0).map(|n| n.__type_name()).ok_or(GraphError::UnknownNode(node))
+    }
+
+    // the impulse a node sent out on an output port during the last run, None if the node did not run
+    pub fn impulse(&self, node: NodeId, output: &str) -> Option<&Impulse<Value>> {
+        let index = self.port(node, output, PortKind::Output).ok()?.index;
+        self.outputs.get(node.0)?.as_ref()?.get(index)
+    }
+
+    // the value a node sent on an output port during the last run, None if it did not SEND anything
+    pub fn output_value(&self, node: NodeId, output: &str) -> Option<&Value> {
+        match self.impulse(node, output)? {
+            Impulse::SEND(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    // same as `output_value`, converted to a `T` with `Value::cast`. None if the value does not convert
+    pub fn output<T: Any + Clone>(&self, node: NodeId, output: &str) -> Option<T> {
+        self.output_value(node, output)?.clone().cast::<T>().ok()
+    }
+
+    // check that the graph can run, returns the order the nodes will be executed in
+    pub fn validate(&self) -> Result<Vec<NodeId>, GraphError> {
+        self.check_inputs()?;
+        self.topological_order()
+    }
+
+    // every required input has to get its value from somewhere
+    pub(crate) fn check_inputs(&self) -> Result<(), GraphError> {
+        for (i, ports) in self.ports.iter().enumerate() {
+            let id = NodeId(i);
+            for port in ports.iter().filter(|p| p.kind == PortKind::Input && p.required) {
+                if !self.is_connected(id, port.index) {
+                    return Err(GraphError::DanglingInput { node: id, input: port.name.to_string() });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the step the graph is on, only advances in clocked mode
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // run every node once
+    pub fn run(&mut self) -> Result<(), GraphError> {
+        let order = self.validate()?;
+        self.run_in_order(&order)
+    }
+
+    // run every node once in an order from `validate`, for running a graph many times without checking it every time
+    pub(crate) fn run_in_order(&mut self, order: &[NodeId]) -> Result<(), GraphError> {
+        self.clear_outputs();
+        self.clear_inputs();
+        self.step(order, None)?;
+        Ok(())
+    }
+
+    // run the graph as a clock. The first step runs every node, then whenever a node returns TICK the clock advances
+    // and that node runs again on the next step, along with anything downstream of it.
+    // every call starts again from tick 0, and stops once a step ends without any TICK or after `max_ticks` more steps.
+    // Returns the tick the graph stopped on
+    pub fn run_clocked(&mut self, max_ticks: u64) -> Result<u64, GraphError> {
+        let order = self.validate()?;
+        self.clear_outputs();
+        self.clear_inputs();
+
+        self.tick = 0;
+        let mut scheduled = self.step(&order, None)?;
+        while !scheduled.is_empty() && self.tick < max_ticks {
+            self.tick += 1;
+            // like every run, every tick starts without what was sent on the one before.
+            // a node that ticks has been reset like after any execution, it runs again on what it got last time
+            // unless its upstream nodes run again and send something new
+            self.clear_inputs();
+            for id in scheduled.iter() {
+                self.redeliver_inputs(*id)?;
+            }
+            scheduled = self.step(&order, Some(&scheduled))?;
+        }
+
+        Ok(self.tick)
+    }
+
+    fn clear_outputs(&mut self) {
+        for outputs in self.outputs.iter_mut() {
+            *outputs = None;
+        }
+        self.errors.clear();
+    }
+
+    // a node that did not run last time can still hold what some of its upstream nodes sent then,
+    // every run starts without it so the node only runs on data from this run. Bindings are set again by `step`
+    fn clear_inputs(&mut self) {
+        let fed: Vec<(NodeId, usize)> = self.edges.iter().map(|e| (e.to, e.input))
+            .chain(self.error_routes().map(|(_, to, input)| (to, input)))
+            .collect();
+        for (node, input) in fed {
+            if self.ports[node.0].iter().any(|p| p.kind == PortKind::Input && p.index == input && p.required) {
+                self.nodes[node.0].__unset_input(input);
+            }
+        }
+    }
+
+    // execute the nodes that are ready, one level at a time. If `scheduled` is given only those nodes, and nodes that get data
+    // during this step, are executed. Returns the nodes that asked to run again on the next tick
+    fn step(&mut self, order: &[NodeId], scheduled: Option<&[NodeId]>) -> Result<Vec<NodeId>, GraphError> {
+        let mut candidates: Vec<bool> = match scheduled {
+            Some(nodes) => {
+                let mut c = vec![false; self.nodes.len()];
+                for id in nodes {
+                    c[id.0] = true;
+                }
+                c
+            },
+            None => vec![true; self.nodes.len()],
+        };
+
+        for node in self.nodes.iter_mut() {
+            node.__set_clock(self.tick);
+        }
+
+        for ((node, input), value) in self.bindings.iter() {
+            self.nodes[node.0].__set_input(*input, value).map_err(|error| GraphError::Port { node: *node, error })?;
+        }
+
+        let levels = match self.levels.take() {
+            Some(levels) => levels,
+            None => self.levels(order),
+        };
+        let result = self.step_levels(&levels, &mut candidates);
+        self.levels = Some(levels);
+        result
+    }
+
+    // nodes on the same level do not depend on each other, so each level is executed together.
+    // async nodes overlap whatever they are waiting on, the results are then handled one node at a time.
+    // every level is a barrier: the next one only starts once the whole level is done, so a slow node also holds back
+    // nodes further down that do not depend on it
+    fn step_levels(&mut self, levels: &[Vec<NodeId>], candidates: &mut [bool]) -> Result<Vec<NodeId>, GraphError> {
+        let mut ticked = vec![];
+        for level in levels.iter() {
+            let wave: Vec<NodeId> = level.iter().copied()
+                .filter(|id| candidates[id.0] && self.nodes[id.0].__ready()) // anything else did not get data from upstream
+                .collect();
+            let results = self.execute_wave(&wave);
+
+            // the whole wave has already run, so when one node halts the run the outputs of the others are still stored and sent on
+            let mut failed = None;
+            for (id, outputs) in wave.into_iter().zip(results) {
+                if let Err(error) = self.handle_outputs(id, outputs, candidates, &mut ticked) {
+                    failed.get_or_insert(error);
+                }
+            }
+            if let Some(error) = failed {
+                return Err(error);
+            }
+        }
+
+        Ok(ticked)
+    }
+
+    // error policies and routing for the outputs of a node that just executed
+    fn handle_outputs(&mut self, id: NodeId, mut outputs: NodeOutputs, candidates: &mut [bool], ticked: &mut Vec<NodeId>) -> Result<(), GraphError> {
+        let mut policy = self.node_error_policies.get(&id).unwrap_or(&self.error_policy).clone();
+
+        while let Some(Impulse::ERROR(error)) = outputs.first() {
+            let mut error = error.clone();
+            error.node = Some(id.0);
+            self.log(id, DeltaMessage::new(LogLevel::Error, error.to_string()));
+
+            match policy {
+                ErrorPolicy::Retry { attempts, then } => {
+                    if attempts == 0 {
+                        policy = *then;
+                        continue;
+                    }
+                    policy = ErrorPolicy::Retry { attempts: attempts - 1, then };
+                    self.redeliver_inputs(id)?;
+                    outputs = self.execute_node(id);
+                },
+                ErrorPolicy::SkipDownstream => {
+                    self.errors.push(error);
+                    break;
+                },
+                ErrorPolicy::Route { node, input } if node != id => {
+                    self.nodes[node.0].__set_input(input, &Value::from_any(error.clone())).map_err(|error| GraphError::Port { node, error })?;
+                    candidates[node.0] = true;
+                    self.errors.push(error);
+                    break;
+                },
+                // a handler that fails under the graph's policy would route to itself, see `error_routes`
+                ErrorPolicy::Halt | ErrorPolicy::Route { .. } => {
+                    self.errors.push(error.clone());
+                    self.outputs[id.0] = Some(outputs);
+                    return Err(GraphError::NodeFailed { node: id, error });
+                },
+            }
+        }
+
+        match outputs.first() {
+            Some(Impulse::TICK) => ticked.push(id),
+            Some(Impulse::ERROR(_)) => {
+                // a failed node does not send anything
+                self.outputs[id.0] = Some(outputs);
+                return Ok(());
+            },
+            _ => {},
+        }
+
+        for edge in self.edges.iter().filter(|e| e.from == id) {
+            if let Some(Impulse::SEND(value)) = outputs.get(edge.output) {
+                self.nodes[edge.to.0].__set_input(edge.input, value).map_err(|error| GraphError::Port { node: edge.to, error })?;
+                candidates[edge.to.0] = true;
+            }
+        }
+
+        self.outputs[id.0] = Some(outputs);
+
+        Ok(())
+    }
+
+    // execute the nodes at the same time, the outputs are in the same order as `wave`.
+    // with worker threads every thread safe node is handed to the workers, the rest are joined on this thread
+    fn execute_wave(&mut self, wave: &[NodeId]) -> Vec<NodeOutputs> {
+        // where each node is in the wave, if it is in it at all
+        let mut position: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        for (i, id) in wave.iter().enumerate() {
+            position[id.0] = Some(i);
+        }
+
+        let threaded = self.threads > 1 && wave.len() > 1;
+        let on_workers: Vec<bool> = (0..self.nodes.len())
+            .map(|i| threaded && position[i].is_some() && self.nodes[i].__as_send().is_some())
+            .collect();
+
+        let mut sendable: VecDeque<(NodeId, &mut dyn SendNode)> = VecDeque::new();
+        let mut futures: Vec<(NodeId, DeltaFuture<'_, NodeOutputs>)> = vec![];
+        for (i, node) in self.nodes.iter_mut().enumerate().filter(|(i, _)| position[*i].is_some()) {
+            if !on_workers[i] {
+                futures.push((NodeId(i), node.__execute_dyn_async()));
+            } else if let Some(node) = node.__as_send() {
+                sendable.push_back((NodeId(i), node));
+            }
+        }
+
+        let (ids, futures): (Vec<NodeId>, Vec<_>) = futures.into_iter().unzip();
+        let mut results: Vec<(NodeId, NodeOutputs)> = if sendable.is_empty() {
+            ids.into_iter().zip(join_all(futures)).collect()
+        } else {
+            let workers = self.threads.min(sendable.len());
+            let queue = Mutex::new(sendable);
+            std::thread::scope(|scope| {
+                // each worker keeps taking the next node until there are none left
+                let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                        let Some((id, node)) = next else { return done };
+                        done.push((id, node.__execute_send()));
+                    }
+                })).collect();
+
+                let mut results: Vec<(NodeId, NodeOutputs)> = ids.into_iter().zip(join_all(futures)).collect();
+                for handle in handles {
+                    let done = handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    results.extend(done);
+                }
+                results
+            })
+        };
+
+        results.sort_by_key(|(id, _)| position[id.0]);
+        results.into_iter().map(|(id, outputs)| self.collect_logs(id, outputs)).collect()
+    }
+
+    fn execute_node(&mut self, id: NodeId) -> NodeOutputs {
+        let outputs = self.nodes[id.0].__execute_dyn();
+        self.collect_logs(id, outputs)
+    }
+
+    fn collect_logs(&mut self, id: NodeId, outputs: NodeOutputs) -> NodeOutputs {
+        if let Some(Impulse::LOG(message)) = outputs.first() {
+            self.log(id, message.clone());
+        }
+        for message in self.nodes[id.0].__drain_logs() {
+            self.log(id, message);
+        }
+        outputs
+    }
+
+    // group the nodes by how deep they are in the graph, a node is one level below the deepest node it depends on.
+    // each level keeps the nodes in the same order as `order`
+    fn levels(&self, order: &[NodeId]) -> Vec<Vec<NodeId>> {
+        let mut upstream: Vec<Vec<NodeId>> = vec![vec![]; self.nodes.len()];
+        for (from, to) in self.dependencies() {
+            upstream[to.0].push(from);
+        }
+
+        let mut depth = vec![0; self.nodes.len()];
+        let mut levels: Vec<Vec<NodeId>> = vec![];
+        for id in order.iter() {
+            depth[id.0] = upstream[id.0].iter().map(|from| depth[from.0] + 1).max().unwrap_or(0);
+            if levels.len() <= depth[id.0] {
+                levels.resize_with(depth[id.0] + 1, Vec::new);
+            }
+            levels[depth[id.0]].push(*id);
+        }
+        levels
+    }
+
+    // routed errors count as edges too, so that the handler runs after the node it handles
+    fn dependencies(&self) -> Vec<(NodeId, NodeId)> {
+        let mut dependencies: Vec<(NodeId, NodeId)> = self.edges.iter().map(|e| (e.from, e.to)).collect();
+        dependencies.extend(self.error_routes().map(|(from, to, _)| (from, to)));
+        dependencies
+    }
+
+    // set the inputs of a node again from its bindings and whatever its upstream nodes sent, so it can be retried or run on the next tick
+    fn redeliver_inputs(&mut self, id: NodeId) -> Result<(), GraphError> {
+        for ((node, input), value) in self.bindings.iter().filter(|((node, _), _)| *node == id) {
+            self.nodes[node.0].__set_input(*input, value).map_err(|error| GraphError::Port { node: *node, error })?;
+        }
+        for edge in self.edges.iter().filter(|e| e.to == id) {
+            if let Some(Some(Impulse::SEND(value))) = self.outputs[edge.from.0].as_ref().map(|o| o.get(edge.output)) {
+                self.nodes[id.0].__set_input(edge.input, value).map_err(|error| GraphError::Port { node: id, error })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn log(&mut self, node: NodeId, mut message: DeltaMessage) {
+        if let Some(sink) = self.log_sink.as_mut() {
+            message.node = Some(node.0);
+            sink.log(&message);
+        }
+    }
+
+    // the index of an input nothing feeds yet, for whatever owns the graph to set with `bind` between runs (see `Loop`)
+    pub(crate) fn free_input(&self, node: NodeId, input: &str) -> Result<usize, GraphError> {
+        let port = self.port(node, input, PortKind::Input)?;
+        self.check_unconnected(node, port)?;
+        Ok(port.index)
+    }
+
+    pub(crate) fn check_output(&self, node: NodeId, output: &str) -> Result<(), GraphError> {
+        self.port(node, output, PortKind::Output).map(|_| ())
+    }
+
+    // set an input claimed with `free_input`, replacing what it was set to before. Checked once the graph runs
+    pub(crate) fn bind(&mut self, node: NodeId, input: usize, value: Value) {
+        self.bindings.insert((node, input), value);
+    }
+
+    // same as `bind`, but the value is set on the node right away so a value of the wrong type is an error here
+    pub(crate) fn bind_checked(&mut self, node: NodeId, input: usize, value: Value) -> Result<(), PortError> {
+        self.nodes[node.0].__set_input(input, &value)?;
+        self.bind(node, input, value);
+        Ok(())
+    }
+
+    // the node forgets the value too
+    pub(crate) fn unbind(&mut self, node: NodeId, input: usize) {
+        self.bindings.remove(&(node, input));
+        self.nodes[node.0].__unset_input(input);
+    }
+
+    fn port(&self, node: NodeId, name: &str, kind: PortKind) -> Result<&PortDescriptor, GraphError> {
+        self.ports(node)?.iter().find(|p| p.kind == kind && p.name == name)
+            .ok_or_else(|| GraphError::UnknownPort { node, port: name.to_owned() })
+    }
+
+    fn is_connected(&self, node: NodeId, input: usize) -> bool {
+        self.bindings.contains_key(&(node, input))
+            || self.edges.iter().any(|e| e.to == node && e.input == input)
+            || self.error_routes().any(|(_, to, i)| to == node && i == input)
+    }
+
+    // (failing node, handler node, handler input) for every node whose errors are routed, by its own policy or the graph's
+    fn error_routes(&self) -> impl Iterator<Item = (NodeId, NodeId, usize)> + '_ {
+        (0..self.nodes.len()).map(NodeId).filter_map(move |from| match self.node_error_policies.get(&from) {
+            Some(policy) => policy.route().map(|(to, input)| (from, to, input)),
+            None => self.error_policy.route().filter(|(to, _)| *to != from).map(|(to, input)| (from, to, input)),
+        })
+    }
+
+    // the handler input of a policy has to exist, take a `DeltaError` and not be fed by anything else.
+    // `from` is None for the policy of the whole graph
+    fn check_route(&self, from: Option<NodeId>, policy: &ErrorPolicy) -> Result<(), GraphError> {
+        if let Some((to, input)) = policy.route() {
+            let port = self.ports(to)?.iter().find(|p| p.kind == PortKind::Input && p.index == input)
+                .ok_or_else(|| GraphError::UnknownPort { node: to, port: input.to_string() })?;
+            self.check_error_input(from.unwrap_or(to), to, port)?;
+        }
+        Ok(())
+    }
+
+    fn check_error_input(&self, from: NodeId, to: NodeId, input: &PortDescriptor) -> Result<(), GraphError> {
+        if !input.error {
+            return Err(GraphError::TypeMismatch { from, output: "error".to_owned(), to, input: input.name.to_string(), expected: input.ty, found: "DeltaError" });
+        }
+        self.check_unconnected(to, input)
+    }
+
+    fn check_unconnected(&self, node: NodeId, input: &PortDescriptor) -> Result<(), GraphError> {
+        if self.is_connected(node, input.index) {
+            return Err(GraphError::InputAlreadyConnected { node, input: input.name.to_string() });
+        }
+        Ok(())
+    }
+
+    // Kahn's algorithm, whatever can not be ordered is part of (or downstream of) a cycle
+    pub(crate) fn topological_order(&self) -> Result<Vec<NodeId>, GraphError> {
+        let mut downstream: Vec<Vec<NodeId>> = vec![vec![]; self.nodes.len()];
+        let mut in_degree = vec![0; self.nodes.len()];
+        for (from, to) in self.dependencies() {
+            downstream[from.0].push(to);
+            in_degree[to.0] += 1;
+        }
+
+        let mut queue: VecDeque<NodeId> = (0..self.nodes.len()).filter(|i| in_degree[*i] == 0).map(NodeId).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+            for to in downstream[id.0].iter() {
+                in_degree[to.0] -= 1;
+                if in_degree[to.0] == 0 {
+                    queue.push_back(*to);
+                }
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let cycle = (0..self.nodes.len()).filter(|i| in_degree[*i] > 0).map(NodeId).collect();
+            return Err(GraphError::Cycle(cycle));
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use delta_lib::{ DeltaError, DeltaMessage, DeltaNode, ErrorKind, Impulse, LogLevel };
+    use delta_lib_macro::{ delta_node_struct, delta_node_impl, RegisterDeltaNode };
+    use crate::graph::{ ErrorPolicy, Graph, GraphError, NodeId };
+    use crate::log::RingBufferSink;
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Add {
+        x: i32,
+        y: i32,
+    }
+
+    #[delta_node_impl( on_exec = "add" )]
+    impl Add {
+        fn add(&mut self) -> Impulse<i32> {
+            Impulse::SEND(self.x + self.y)
+        }
+    }
+
+    // only lets positive numbers through
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Positive {
+        x: i32,
+    }
+
+    #[delta_node_impl( on_exec = "filter" )]
+    impl Positive {
+        fn filter(&mut self) -> Impulse<i32> {
+            if self.x > 0 { Impulse::SEND(self.x) } else { Impulse::NOOP }
+        }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Split {
+        x: i32,
+
+        #[delta_output]
+        half: Impulse<i32>,
+    }
+
+    #[delta_node_impl( on_exec = "split" )]
+    impl Split {
+        fn split(&mut self) -> Impulse<i32> {
+            self.half = Impulse::SEND(self.x / 2);
+            Impulse::SEND(self.x - self.x / 2)
+        }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Scale {
+        x: f64,
+    }
+
+    #[delta_node_impl( on_exec = "scale" )]
+    impl Scale {
+        fn scale(&mut self) -> Impulse<f64> {
+            Impulse::SEND(self.x * 2.0)
+        }
+    }
+
+    #[test]
+    fn chain() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(Add::__initialize());
+        let b = graph.add_node(Add::__initialize());
+        graph.set_input(a, "x", 1).unwrap();
+        graph.set_input(a, "y", 2).unwrap();
+        graph.connect(a, "out", b, "x").unwrap();
+        graph.set_input(b, "y", 10).unwrap();
+
+        assert_eq!(Ok(vec![a, b]), graph.validate());
+        graph.run().unwrap();
+        assert_eq!(Some(3), graph.output::<i32>(a, "out"));
+        assert_eq!(Some(13), graph.output::<i32>(b, "out"));
+
+        // bindings are applied again on every run
+        graph.run().unwrap();
+        assert_eq!(Some(13), graph.output::<i32>(b, "out"));
+    }
+
+    #[test]
+    fn fan_out_and_output_ports() {
+        let mut graph = Graph::new();
+        let split = graph.add_node(Split::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        let double = graph.add_node(Add::__initialize());
+        graph.set_input(split, "x", 7).unwrap();
+        graph.connect(split, "out", add, "x").unwrap();
+        graph.connect(split, "half", add, "y").unwrap();
+        graph.connect(split, "half", double, "x").unwrap();
+        graph.connect(split, "half", double, "y").unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(4), graph.output::<i32>(split, "out"));
+        assert_eq!(Some(3), graph.output::<i32>(split, "half"));
+        assert_eq!(Some(7), graph.output::<i32>(add, "out"));
+        assert_eq!(Some(6), graph.output::<i32>(double, "out"));
+    }
+
+    #[test]
+    fn noop_stops_downstream() {
+        let mut graph = Graph::new();
+        let filter = graph.add_node(Positive::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        graph.set_input(filter, "x", -5).unwrap();
+        graph.connect(filter, "out", add, "x").unwrap();
+        graph.set_input(add, "y", 1).unwrap();
+
+        graph.run().unwrap();
+        assert!(matches!(graph.impulse(filter, "out"), Some(Impulse::NOOP)));
+        assert!(graph.impulse(add, "out").is_none());
+    }
+
+    #[test]
+    fn branches() {
+        use logic::{ If, Merge };
+
+        let build = |condition: bool| {
+            let mut graph = Graph::new();
+            let branch = graph.add_node(If::__initialize());
+            let add = graph.add_node(Add::__initialize());
+            let scale = graph.add_node(Scale::__initialize());
+            let merge = graph.add_node(Merge::__initialize());
+            graph.set_input(branch, "condition", condition).unwrap();
+            graph.set_input(branch, "value", 5).unwrap();
+            graph.connect(branch, "out", add, "x").unwrap();
+            graph.set_input(add, "y", 1).unwrap();
+            graph.connect(branch, "otherwise", scale, "x").unwrap();
+            graph.connect(add, "out", merge, "a").unwrap();
+            graph.connect(scale, "out", merge, "b").unwrap();
+            graph.run().unwrap();
+            (graph, add, scale, merge)
+        };
+
+        // only the branch that was taken runs, the merge sends whichever one did
+        let (graph, add, scale, merge) = build(true);
+        assert_eq!(Some(6), graph.output::<i32>(add, "out"));
+        assert!(graph.impulse(scale, "out").is_none());
+        assert_eq!(Some(6), graph.output::<i32>(merge, "out"));
+
+        let (graph, add, _, merge) = build(false);
+        assert!(graph.impulse(add, "out").is_none());
+        assert_eq!(Some(10.0), graph.output::<f64>(merge, "out"));
+
+        // a value port can be connected to any type, it only fails if what is sent does not convert
+        let mut graph = Graph::new();
+        let merge = graph.add_node(Merge::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        graph.set_input(merge, "a", "five").unwrap();
+        graph.connect(merge, "out", add, "x").unwrap();
+        graph.set_input(add, "y", 1).unwrap();
+        assert!(matches!(graph.run(), Err(GraphError::Port { node, .. }) if node == add));
+    }
+
+    #[test]
+    fn mixed_node_types() {
+        use delta_lib::DynDeltaNode;
+
+        let nodes: Vec<Box<dyn DynDeltaNode>> = vec![Add::__initialize(), Scale::__initialize(), Split::__initialize()];
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = nodes.into_iter().map(|n| graph.add_node(n)).collect();
+        graph.set_input(ids[0], "x", 1).unwrap();
+        graph.set_input(ids[0], "y", 2).unwrap();
+        graph.set_input(ids[1], "x", 1.5).unwrap();
+        graph.connect(ids[0], "out", ids[2], "x").unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(3.0), graph.output::<f64>(ids[1], "out"));
+        assert_eq!(Some(1), graph.output::<i32>(ids[2], "half"));
+
+        assert!(graph.node::<Scale>(ids[1]).is_some());
+        assert!(graph.node::<Add>(ids[1]).is_none());
+        assert!(graph.node_mut::<Split>(ids[2]).unwrap().__type_name().ends_with("Split"));
+    }
+
+    // an input with a default does not have to be connected
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Offset {
+        x: i32,
+
+        #[delta_default(100)]
+        offset: i32,
+    }
+
+    #[delta_node_impl( on_exec = "offset" )]
+    impl Offset {
+        fn offset(&mut self) -> Impulse<i32> {
+            Impulse::SEND(self.x + self.offset)
+        }
+    }
+
+    #[test]
+    fn optional_inputs() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(Offset::__initialize());
+        let b = graph.add_node(Offset::__initialize());
+        graph.set_input(a, "x", 1).unwrap();
+        graph.connect(a, "out", b, "x").unwrap();
+        graph.connect(a, "out", b, "offset").unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(101), graph.output::<i32>(a, "out"));
+        assert_eq!(Some(202), graph.output::<i32>(b, "out"));
+    }
+
+    // sums up everything it has seen
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Accumulate {
+        x: i32,
+
+        #[delta_ignore]
+        total: i32,
+    }
+
+    #[delta_node_impl( on_exec = "accumulate" )]
+    impl Accumulate {
+        fn accumulate(&mut self) -> Impulse<i32> {
+            self.total += self.x;
+            Impulse::SEND(self.total)
+        }
+    }
+
+    #[test]
+    fn nodes_persist_across_runs() {
+        let mut graph = Graph::new();
+        let acc = graph.add_node(Accumulate::__initialize());
+        graph.set_input(acc, "x", 5).unwrap();
+
+        for _ in 0..3 {
+            graph.run().unwrap();
+        }
+        assert_eq!(Some(15), graph.output::<i32>(acc, "out"));
+        assert_eq!(15, graph.node::<Accumulate>(acc).unwrap().total);
+
+        // the levels kept from the last run are worked out again once the graph changes
+        let add = graph.add_node(Add::__initialize());
+        graph.connect(acc, "out", add, "x").unwrap();
+        graph.connect(acc, "out", add, "y").unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(40), graph.output::<i32>(add, "out"));
+    }
+
+    // counts up to `limit`, asking for another tick until it gets there
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Counter {
+        #[delta_noreset]
+        #[delta_default(3)]
+        limit: u64,
+
+        #[delta_output]
+        count: Impulse<i32>,
+
+        #[delta_ignore]
+        seen_ticks: Vec<u64>,
+    }
+
+    #[delta_node_impl( on_exec = "count" )]
+    impl Counter {
+        fn count(&mut self) -> Impulse<()> {
+            self.seen_ticks.push(self.__current_tick());
+            self.count = Impulse::SEND(self.seen_ticks.len() as i32);
+            if (self.seen_ticks.len() as u64) < self.limit { Impulse::TICK } else { Impulse::NOOP }
+        }
+    }
+
+    // sends what it got three times, one per tick
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Echo {
+        x: i32,
+
+        #[delta_output]
+        echo: Impulse<i32>,
+
+        #[delta_ignore]
+        seen: Vec<i32>,
+    }
+
+    #[delta_node_impl( on_exec = "echo" )]
+    impl Echo {
+        fn echo(&mut self) -> Impulse<()> {
+            self.seen.push(self.x);
+            self.echo = Impulse::SEND(self.x);
+            if self.seen.len() < 3 { Impulse::TICK } else { Impulse::NOOP }
+        }
+    }
+
+    #[test]
+    fn clocked() {
+        let mut graph = Graph::new();
+        let counter = graph.add_node(Counter::__initialize());
+        let acc = graph.add_node(Accumulate::__initialize());
+        graph.connect(counter, "count", acc, "x").unwrap();
+
+        assert_eq!(Ok(2), graph.run_clocked(100));
+        assert_eq!(2, graph.tick());
+        assert_eq!(vec![0, 1, 2], graph.node::<Counter>(counter).unwrap().seen_ticks);
+        assert_eq!(Some(6), graph.output::<i32>(acc, "out")); // 1 + 2 + 3
+
+        // the clock starts over on every call
+        graph.set_input(counter, "limit", 6_u64).unwrap();
+        assert_eq!(Ok(2), graph.run_clocked(100));
+        assert_eq!(vec![0, 1, 2, 0, 1, 2], graph.node::<Counter>(counter).unwrap().seen_ticks);
+
+        // a node only runs on what was sent during the same tick, the constant is only sent on the first one
+        let mut graph = Graph::new();
+        let counter = graph.add_node(Counter::__initialize());
+        let constant = graph.add_node(Add::__initialize());
+        let sum = graph.add_node(Add::__initialize());
+        graph.set_input(constant, "x", 1).unwrap();
+        graph.set_input(constant, "y", 2).unwrap();
+        graph.connect(counter, "count", sum, "x").unwrap();
+        graph.connect(constant, "out", sum, "y").unwrap();
+        assert_eq!(Ok(2), graph.run_clocked(100));
+        assert_eq!(Some(4), graph.output::<i32>(sum, "out")); // 1 + 3 on tick 0, not 3 + 3 on tick 2
+
+        // a node that ticks gets the same inputs again, even though nothing upstream of it runs again
+        let mut graph = Graph::new();
+        let add = graph.add_node(Add::__initialize());
+        let echo = graph.add_node(Echo::__initialize());
+        graph.set_input(add, "x", 1).unwrap();
+        graph.set_input(add, "y", 2).unwrap();
+        graph.connect(add, "out", echo, "x").unwrap();
+        assert_eq!(Ok(2), graph.run_clocked(100));
+        assert_eq!(vec![3, 3, 3], graph.node::<Echo>(echo).unwrap().seen);
+
+        // the clock stops at max_ticks even if nodes still want to tick
+        let mut graph = Graph::new();
+        let counter = graph.add_node(Counter::__initialize());
+        graph.set_input(counter, "limit", 1000_u64).unwrap();
+        assert_eq!(Ok(10), graph.run_clocked(10));
+        assert_eq!(11, graph.node::<Counter>(counter).unwrap().seen_ticks.len());
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Chatty {
+        x: i32,
+    }
+
+    #[delta_node_impl( on_exec = "chat" )]
+    impl Chatty {
+        fn chat(&mut self) -> Impulse<i32> {
+            if self.x < 0 {
+                return Impulse::LOG(DeltaMessage::new(LogLevel::Warn, "negative input"));
+            }
+            self.__log_message(DeltaMessage::new(LogLevel::Info, "got input").with_field("x", self.x));
+            Impulse::SEND(self.x)
+        }
+    }
+
+    #[test]
+    fn logging() {
+        let buffer = RingBufferSink::new(10);
+        let mut graph = Graph::new();
+        graph.set_log_sink(Box::new(buffer.clone()));
+        let a = graph.add_node(Chatty::__initialize());
+        let b = graph.add_node(Chatty::__initialize());
+        graph.set_input(a, "x", 4).unwrap();
+        graph.set_input(b, "x", -1).unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(4), graph.output::<i32>(a, "out"));
+
+        let mut expected_a = DeltaMessage::new(LogLevel::Info, "got input").with_field("x", 4);
+        expected_a.node = Some(a.0);
+        let mut expected_b = DeltaMessage::new(LogLevel::Warn, "negative input");
+        expected_b.node = Some(b.0);
+        assert_eq!(vec![expected_a, expected_b], buffer.messages());
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Divide {
+        x: i32,
+        y: i32,
+    }
+
+    #[delta_node_impl( on_exec = "divide" )]
+    impl Divide {
+        fn divide(&mut self) -> Result<Impulse<i32>, DeltaError> {
+            match self.x.checked_div(self.y) {
+                Some(z) => Ok(Impulse::SEND(z)),
+                None => Err(DeltaError::new(ErrorKind::DivisionByZero, "y is 0")),
+            }
+        }
+    }
+
+    // fails the first `failures` times it runs
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Flaky {
+        x: i32,
+
+        #[delta_noreset]
+        #[delta_default(2)]
+        failures: u32,
+    }
+
+    #[delta_node_impl( on_exec = "flaky" )]
+    impl Flaky {
+        fn flaky(&mut self) -> Result<Impulse<i32>, String> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err("not yet".to_owned());
+            }
+            Ok(Impulse::SEND(self.x))
+        }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Handler {
+        error: DeltaError,
+    }
+
+    #[delta_node_impl( on_exec = "handle" )]
+    impl Handler {
+        fn handle(&mut self) -> Impulse<String> {
+            Impulse::SEND(self.error.to_string())
+        }
+    }
+
+    fn divide_graph(y: i32) -> (Graph, NodeId, NodeId) {
+        let mut graph = Graph::new();
+        let div = graph.add_node(Divide::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        graph.set_input(div, "x", 10).unwrap();
+        graph.set_input(div, "y", y).unwrap();
+        graph.connect(div, "out", add, "x").unwrap();
+        graph.set_input(add, "y", 1).unwrap();
+        (graph, div, add)
+    }
+
+    #[test]
+    fn error_policies() {
+        let (mut graph, div, add) = divide_graph(2);
+        graph.run().unwrap();
+        assert_eq!(Some(6), graph.output::<i32>(add, "out"));
+
+        // halting is the default
+        let (mut graph, div_0, _) = divide_graph(0);
+        let mut error = DeltaError::new(ErrorKind::DivisionByZero, "y is 0");
+        error.node = Some(div_0.0);
+        assert_eq!(Err(GraphError::NodeFailed { node: div_0, error: error.clone() }), graph.run());
+        assert_eq!(vec![error.clone()], graph.errors());
+
+        // skipping lets the rest of the graph carry on
+        let (mut graph, _, add) = divide_graph(0);
+        let other = graph.add_node(Add::__initialize());
+        graph.set_input(other, "x", 1).unwrap();
+        graph.set_input(other, "y", 1).unwrap();
+        graph.set_error_policy(ErrorPolicy::SkipDownstream).unwrap();
+        graph.run().unwrap();
+        assert!(matches!(graph.impulse(div, "out"), Some(Impulse::ERROR(_))));
+        assert!(graph.impulse(add, "out").is_none());
+        assert_eq!(Some(2), graph.output::<i32>(other, "out"));
+        assert_eq!(vec![error.clone()], graph.errors());
+
+        // routing sends the error to another node, the handler runs after the divide even though it was added first
+        let mut graph = Graph::new();
+        let handler = graph.add_node(Handler::__initialize());
+        let div = graph.add_node(Divide::__initialize());
+        graph.set_input(div, "x", 10).unwrap();
+        graph.set_input(div, "y", 0).unwrap();
+        assert_eq!(Err(GraphError::TypeMismatch { from: div, output: "error".to_owned(), to: div, input: "x".to_owned(), expected: "i32", found: "DeltaError" }),
+            graph.route_errors(div, div, "x"));
+        graph.route_errors(div, handler, "error").unwrap();
+        graph.run().unwrap();
+        let mut error = DeltaError::new(ErrorKind::DivisionByZero, "y is 0");
+        error.node = Some(div.0);
+        assert_eq!(Some(error.to_string()), graph.output::<String>(handler, "out"));
+    }
+
+    #[test]
+    fn retries() {
+        let buffer = RingBufferSink::new(10);
+        let mut graph = Graph::new();
+        graph.set_log_sink(Box::new(buffer.clone()));
+        let flaky = graph.add_node(Flaky::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        graph.set_input(flaky, "x", 3).unwrap();
+        graph.connect(flaky, "out", add, "x").unwrap();
+        graph.connect(flaky, "out", add, "y").unwrap();
+        graph.set_error_policy(ErrorPolicy::Retry { attempts: 2, then: Box::new(ErrorPolicy::Halt) }).unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(6), graph.output::<i32>(add, "out"));
+        assert_eq!(2, buffer.messages().iter().filter(|m| m.level == LogLevel::Error).count());
+
+        // once it runs out of attempts the fallback policy takes over
+        let mut graph = Graph::new();
+        let flaky = graph.add_node(Flaky::__initialize());
+        graph.set_input(flaky, "x", 3).unwrap();
+        graph.set_input(flaky, "failures", 5_u32).unwrap();
+        graph.set_node_error_policy(flaky, ErrorPolicy::Retry { attempts: 1, then: Box::new(ErrorPolicy::SkipDownstream) }).unwrap();
+        graph.run().unwrap();
+        assert_eq!(1, graph.errors().len());
+        // the retry got the bound `failures` again, so it only went down by one
+        assert_eq!(4, graph.node::<Flaky>(flaky).unwrap().failures);
+    }
+
+    #[test]
+    fn routed_errors() {
+        // the handler input is found by the flag on its port, not by the name of its type
+        let mut graph = Graph::new();
+        let handler = graph.add_node(Handler::__initialize());
+        assert!(graph.ports(handler).unwrap()[0].error);
+        assert_eq!(Err(GraphError::UnknownNode(NodeId(99))), graph.set_error_policy(ErrorPolicy::Route { node: NodeId(99), input: 0 }));
+        assert_eq!(Err(GraphError::UnknownPort { node: handler, port: "5".to_owned() }), graph.set_error_policy(ErrorPolicy::Route { node: handler, input: 5 }));
+        let div = graph.add_node(Divide::__initialize());
+        assert!(graph.set_error_policy(ErrorPolicy::Route { node: div, input: 0 }).is_err());
+
+        // routing after the retries ran out still orders the handler after the failing node
+        let flaky = graph.add_node(Flaky::__initialize());
+        graph.set_input(flaky, "x", 3).unwrap();
+        graph.set_input(flaky, "failures", 5_u32).unwrap();
+        graph.set_node_error_policy(flaky, ErrorPolicy::Retry { attempts: 1, then: Box::new(ErrorPolicy::Route { node: handler, input: 0 }) }).unwrap();
+        assert!(graph.set_error_policy(ErrorPolicy::Route { node: handler, input: 0 }).is_err());
+        graph.set_input(div, "x", 1).unwrap();
+        graph.set_input(div, "y", 1).unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(graph.errors()[0].to_string()), graph.output::<String>(handler, "out"));
+
+        // the graph's policy routes every other node, which makes the handler input connected
+        let mut graph = Graph::new();
+        let handler = graph.add_node(Handler::__initialize());
+        let div = graph.add_node(Divide::__initialize());
+        graph.set_input(div, "x", 10).unwrap();
+        graph.set_input(div, "y", 0).unwrap();
+        graph.set_error_policy(ErrorPolicy::Route { node: handler, input: 0 }).unwrap();
+        assert_eq!(Err(GraphError::InputAlreadyConnected { node: handler, input: "error".to_owned() }), graph.route_errors(div, handler, "error"));
+        graph.run().unwrap();
+        let mut error = DeltaError::new(ErrorKind::DivisionByZero, "y is 0");
+        error.node = Some(div.0);
+        assert_eq!(Some(error.to_string()), graph.output::<String>(handler, "out"));
+    }
+
+    #[test]
+    fn halting_finishes_the_wave() {
+        let (mut graph, div, _) = divide_graph(0);
+        let other = graph.add_node(Add::__initialize());
+        graph.set_input(other, "x", 1).unwrap();
+        graph.set_input(other, "y", 1).unwrap();
+        assert!(matches!(graph.run(), Err(GraphError::NodeFailed { node, .. }) if node == div));
+        // `other` ran next to the divide, so its outputs are still there
+        assert_eq!(Some(2), graph.output::<i32>(other, "out"));
+    }
+
+    // sends true and false in turns, one per run
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Toggle {
+        #[delta_ignore]
+        on: bool,
+    }
+
+    #[delta_node_impl( on_exec = "toggle" )]
+    impl Toggle {
+        fn toggle(&mut self) -> Impulse<bool> {
+            self.on = !self.on;
+            Impulse::SEND(self.on)
+        }
+    }
+
+    // not a `delta_lib::Value`, it only has the same name
+    mod local {
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct Value(pub i32);
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Unwrap {
+        value: local::Value,
+    }
+
+    #[delta_node_impl( on_exec = "unwrap" )]
+    impl Unwrap {
+        fn unwrap(&mut self) -> Impulse<i32> {
+            Impulse::SEND(self.value.0)
+        }
+    }
+
+    #[test]
+    fn dynamic_ports() {
+        use logic::If;
+
+        // only ports of the actual `Value` type connect to anything
+        let mut graph = Graph::new();
+        let add = graph.add_node(Add::__initialize());
+        let branch = graph.add_node(If::__initialize());
+        let unwrap = graph.add_node(Unwrap::__initialize());
+        assert!(graph.ports(branch).unwrap().iter().any(|p| p.name == "value" && p.dynamic));
+        assert!(!graph.ports(unwrap).unwrap().iter().any(|p| p.dynamic));
+        graph.connect(add, "out", branch, "value").unwrap();
+        graph.connect(branch, "out", add, "x").unwrap();
+        assert_eq!(Err(GraphError::TypeMismatch { from: add, output: "out".to_owned(), to: unwrap, input: "value".to_owned(), expected: "local::Value", found: "i32" }),
+            graph.connect(add, "out", unwrap, "value"));
+    }
+
+    // sends the value on `out` if the condition holds, on `otherwise` if it does not
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Branch {
+        condition: bool,
+        value: i32,
+
+        #[delta_output]
+        otherwise: Impulse<i32>,
+    }
+
+    #[delta_node_impl( on_exec = "branch" )]
+    impl Branch {
+        fn branch(&mut self) -> Impulse<i32> {
+            if self.condition {
+                Impulse::SEND(self.value)
+            } else {
+                self.otherwise = Impulse::SEND(self.value);
+                Impulse::NOOP
+            }
+        }
+    }
+
+    #[test]
+    fn inputs_cleared_between_runs() {
+        // each branch feeds one input of the add, so it never has both
+        let mut graph = Graph::new();
+        let toggle = graph.add_node(Toggle::__initialize());
+        let branch = graph.add_node(Branch::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        graph.connect(toggle, "out", branch, "condition").unwrap();
+        graph.set_input(branch, "value", 5).unwrap();
+        graph.connect(branch, "out", add, "x").unwrap();
+        graph.connect(branch, "otherwise", add, "y").unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(5), graph.output::<i32>(branch, "out"));
+        assert!(graph.impulse(add, "out").is_none());
+
+        // what the first run sent to x is gone by the second
+        graph.run().unwrap();
+        assert_eq!(Some(5), graph.output::<i32>(branch, "otherwise"));
+        assert!(graph.impulse(add, "out").is_none());
+        assert!(!graph.node::<Add>(add).unwrap().__is_ready());
+    }
+
+    #[test]
+    fn errors() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(Add::__initialize());
+        let b = graph.add_node(Add::__initialize());
+        let scale = graph.add_node(Scale::__initialize());
+
+        assert_eq!(Err(GraphError::UnknownNode(NodeId(5))), graph.connect(a, "out", NodeId(5), "x").map(|_| ()));
+        assert_eq!(Err(GraphError::UnknownPort { node: b, port: "z".to_owned() }), graph.connect(a, "out", b, "z").map(|_| ()));
+        assert_eq!(Err(GraphError::TypeMismatch { from: a, output: "out".to_owned(), to: scale, input: "x".to_owned(), expected: "f64", found: "i32" }),
+            graph.connect(a, "out", scale, "x").map(|_| ()));
+
+        graph.connect(a, "out", b, "x").unwrap();
+        assert_eq!(Err(GraphError::InputAlreadyConnected { node: b, input: "x".to_owned() }), graph.set_input(b, "x", 1));
+
+        graph.set_input(a, "x", 1).unwrap();
+        assert_eq!(Err(GraphError::DanglingInput { node: a, input: "y".to_owned() }), graph.run());
+
+        graph.connect(b, "out", a, "y").unwrap();
+        graph.set_input(b, "y", 1).unwrap();
+        graph.set_input(scale, "x", 1.0).unwrap();
+        assert_eq!(Err(GraphError::Cycle(vec![a, b])), graph.run());
+    }
+
+    // finishes after a delay, woken by a thread so it works without a runtime
+    struct Sleep {
+        until: std::time::Instant,
+        started: bool,
+    }
+
+    impl std::future::Future for Sleep {
+        type Output = ();
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<()> {
+            if std::time::Instant::now() >= self.until {
+                return std::task::Poll::Ready(());
+            }
+            if !self.started {
+                self.started = true;
+                let (waker, until) = (cx.waker().clone(), self.until);
+                std::thread::spawn(move || {
+                    std::thread::sleep(until.saturating_duration_since(std::time::Instant::now()));
+                    waker.wake();
+                });
+            }
+            std::task::Poll::Pending
+        }
+    }
+
+    fn sleep(millis: u64) -> Sleep {
+        Sleep { until: std::time::Instant::now() + std::time::Duration::from_millis(millis), started: false }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Slow {
+        x: i32,
+
+        #[delta_ignore]
+        in_flight: Arc<AtomicUsize>,
+
+        #[delta_ignore]
+        most: Arc<AtomicUsize>, // the most nodes sharing `in_flight` that were running at once
+    }
+
+    #[delta_node_impl]
+    impl Slow {
+        async fn __pre_execute(&mut self) {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(now, Ordering::SeqCst);
+            sleep(20).await;
+        }
+
+        #[delta(on_exec)]
+        async fn fetch(&mut self) -> Result<Impulse<i32>, DeltaError> {
+            sleep(20).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            if self.x < 0 {
+                return Err(DeltaError::new(ErrorKind::InvalidInput, "negative"));
+            }
+            Ok(Impulse::SEND(self.x * 10))
+        }
+    }
+
+    #[test]
+    fn async_nodes() {
+        // executing an async node directly blocks until it is done
+        let mut slow = Slow::__initialize();
+        slow.__set_x(1);
+        assert_eq!(Impulse::SEND(10), slow.__execute());
+
+        // both branches wait at the same time, then the sync Add runs once both have sent
+        let (in_flight, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| {
+            let mut slow = Slow::__initialize();
+            slow.in_flight = in_flight.clone();
+            slow.most = most.clone();
+            graph.add_node(slow)
+        });
+        let add = graph.add_node(Add::__initialize());
+        graph.connect(a, "out", add, "x").unwrap();
+        graph.connect(b, "out", add, "y").unwrap();
+        graph.set_input(a, "x", 2).unwrap();
+        graph.set_input(b, "x", 3).unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(2, most.load(Ordering::SeqCst));
+        assert_eq!(Some(50), graph.output::<i32>(add, "out"));
+
+        // async errors go through the error policies like any other
+        let mut graph = Graph::new();
+        let a = graph.add_node(Slow::__initialize());
+        graph.set_input(a, "x", -3).unwrap();
+        assert!(matches!(graph.run(), Err(GraphError::NodeFailed { node, .. }) if node == a));
+    }
+
+    // stays busy until `together` nodes sharing `in_flight` are running at once, which only happens on worker threads.
+    // gives up after a while, so running them one after the other fails the test instead of hanging it
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Spin {
+        x: i32,
+
+        #[delta_ignore]
+        together: usize,
+
+        #[delta_ignore]
+        in_flight: Arc<AtomicUsize>,
+
+        #[delta_ignore]
+        most: Arc<AtomicUsize>,
+    }
+
+    #[delta_node_impl]
+    impl Spin {
+        fn __on_execute(&mut self) -> Impulse<i32> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(now, Ordering::SeqCst);
+            let start = std::time::Instant::now();
+            while self.most.load(Ordering::SeqCst) < self.together && start.elapsed() < std::time::Duration::from_secs(5) {
+                std::hint::spin_loop();
+            }
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Impulse::SEND(self.x)
+        }
+    }
+
+    // `Rc` is not `Send`, so this node has to stay on the thread running the graph
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    #[delta_thread_local]
+    struct Shared {
+        x: i32,
+
+        #[delta_ignore]
+        seen: std::rc::Rc<std::cell::Cell<i32>>,
+    }
+
+    #[delta_node_impl]
+    impl Shared {
+        fn __on_execute(&mut self) -> Impulse<i32> {
+            self.seen.set(self.x);
+            Impulse::SEND(self.x)
+        }
+    }
+
+    #[test]
+    fn threads() {
+        let mut graph = Graph::new();
+        graph.set_threads(4);
+        let (in_flight, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let spins: Vec<NodeId> = (0..3).map(|_| {
+            let mut spin = Spin::__initialize();
+            spin.together = 3;
+            spin.in_flight = in_flight.clone();
+            spin.most = most.clone();
+            graph.add_node(spin)
+        }).collect();
+        let shared = graph.add_node(Shared::__initialize());
+        let add = graph.add_node(Add::__initialize());
+        let total = graph.add_node(Add::__initialize());
+        for (i, spin) in spins.iter().enumerate() {
+            graph.set_input(*spin, "x", i as i32 + 1).unwrap();
+        }
+        graph.set_input(shared, "x", 10).unwrap();
+        graph.connect(spins[0], "out", add, "x").unwrap();
+        graph.connect(spins[1], "out", add, "y").unwrap();
+        graph.connect(add, "out", total, "x").unwrap();
+        graph.connect(spins[2], "out", total, "y").unwrap();
+
+        graph.run().unwrap();
+        // all three spins were on the workers at once
+        assert_eq!(3, most.load(Ordering::SeqCst));
+        assert_eq!(Some(6), graph.output::<i32>(total, "out"));
+        assert_eq!(10, graph.node::<Shared>(shared).unwrap().seen.get());
+    }
+
+    // a type `Value` has no variant for travels as an opaque value
+    #[derive(Debug, Clone, PartialEq, Default)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct MakePoint {
+        x: i32,
+        y: i32,
+    }
+
+    #[delta_node_impl( on_exec = "make" )]
+    impl MakePoint {
+        fn make(&mut self) -> Impulse<Point> {
+            Impulse::SEND(Point { x: self.x, y: self.y })
+        }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Length {
+        point: Point,
+    }
+
+    #[delta_node_impl( on_exec = "length" )]
+    impl Length {
+        fn length(&mut self) -> Impulse<f64> {
+            Impulse::SEND(f64::from(self.point.x).hypot(f64::from(self.point.y)))
+        }
+    }
+
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Digits {
+        x: u32,
+    }
+
+    #[delta_node_impl( on_exec = "digits" )]
+    impl Digits {
+        fn digits(&mut self) -> Impulse<Vec<u8>> {
+            Impulse::SEND(self.x.to_string().bytes().map(|b| b - b'0').collect())
+        }
+    }
+
+    #[test]
+    fn values() {
+        use delta_lib::{ PortError, Value };
+
+        let mut graph = Graph::new();
+        let make = graph.add_node(MakePoint::__initialize());
+        let length = graph.add_node(Length::__initialize());
+        graph.set_input_value(make, "x", Value::Int(3)).unwrap();
+        graph.set_input_value(make, "y", Value::Float(4.0)).unwrap();
+        graph.connect(make, "out", length, "point").unwrap();
+
+        graph.run().unwrap();
+        assert_eq!(Some(Point { x: 3, y: 4 }), graph.output::<Point>(make, "out"));
+        assert!(graph.output_value(make, "out").unwrap().kind().ends_with("Point"));
+        assert_eq!(Some(&Value::Float(5.0)), graph.output_value(length, "out"));
+        assert_eq!(Some(5), graph.output::<i32>(length, "out")); // a whole float converts to an integer without losing anything
+        assert_eq!(None, graph.output::<String>(length, "out"));
+
+        // a binding has to convert to the type of its input
+        let other = graph.add_node(Length::__initialize());
+        assert_eq!(Err(GraphError::Port { node: other, error: PortError::TypeMismatch { port: "point".to_owned(), expected: "Point", found: "int" } }),
+            graph.set_input_value(other, "point", Value::Int(1)));
+        graph.set_input(other, "point", Point { x: 0, y: 2 }).unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(2.0), graph.output::<f64>(other, "out"));
+
+        // bytes are sent the same way `Value::from` converts them
+        let digits = graph.add_node(Digits::__initialize());
+        graph.set_input(digits, "x", 42_u32).unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(&Value::List(vec![Value::Int(4), Value::Int(2)])), graph.output_value(digits, "out"));
+        assert_eq!(Some(vec![4_u8, 2]), graph.output::<Vec<u8>>(digits, "out"));
+    }
+}
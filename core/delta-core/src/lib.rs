@@ -0,0 +1,14 @@
+pub mod cli;
+mod composite;
+mod graph;
+pub mod log;
+mod loops;
+pub mod plugin;
+pub mod program;
+pub use composite::{ Composite, register_composite };
+pub use graph::{ Graph, GraphError, ErrorPolicy, NodeId, Edge };
+pub use log::{ LogSink, StderrSink, JsonLinesSink, RingBufferSink };
+pub use loops::Loop;
+pub use plugin::{ PluginLoader, PluginError };
+pub use program::{ CompositeDefinition, Program, ProgramEdge, ProgramError, ProgramMetadata, ProgramNode, ProgramPort, LoadedProgram, load_program,
+    save_program };
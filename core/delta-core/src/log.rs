@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{ Arc, Mutex };
+use delta_lib::{ DeltaMessage, LogLevel };
+
+// somewhere for the engine to put the messages nodes log
+pub trait LogSink {
+    fn log(&mut self, message: &DeltaMessage);
+}
+
+// prints every message at or above a level to stderr
+pub struct StderrSink {
+    pub min_level: LogLevel,
+}
+
+impl StderrSink {
+    pub fn new(min_level: LogLevel) -> StderrSink {
+        StderrSink { min_level }
+    }
+}
+
+impl LogSink for StderrSink {
+    fn log(&mut self, message: &DeltaMessage) {
+        if message.level >= self.min_level {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+// writes one JSON object per message, e.g. to a file for inspecting a run afterwards
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl JsonLinesSink<std::io::BufWriter<std::fs::File>> {
+    pub fn create<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Ok(JsonLinesSink::new(std::io::BufWriter::new(std::fs::File::create(path)?)))
+    }
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> JsonLinesSink<W> {
+        JsonLinesSink { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub fn message_to_json(message: &DeltaMessage) -> serde_json::Value {
+    let fields: serde_json::Map<String, serde_json::Value> = message.fields.iter()
+        .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+        .collect();
+    serde_json::json!({
+        "level": message.level.to_string(),
+        "node": message.node,
+        "tick": message.tick,
+        "text": message.text,
+        "fields": fields,
+    })
+}
+
+impl<W: Write> LogSink for JsonLinesSink<W> {
+    fn log(&mut self, message: &DeltaMessage) {
+        // a failing log write should never take down the graph, so errors are dropped
+        let _ = writeln!(self.writer, "{}", message_to_json(message));
+        let _ = self.writer.flush();
+    }
+}
+
+// keeps the last `capacity` messages in memory.
+// clones share the same buffer, so keep a clone around to read the messages after handing one to a graph
+#[derive(Clone)]
+pub struct RingBufferSink {
+    capacity: usize,
+    messages: Arc<Mutex<VecDeque<DeltaMessage>>>,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> RingBufferSink {
+        RingBufferSink { capacity, messages: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))) }
+    }
+
+    // oldest message first
+    pub fn messages(&self) -> Vec<DeltaMessage> {
+        self.messages.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+impl LogSink for RingBufferSink {
+    fn log(&mut self, message: &DeltaMessage) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut messages = self.messages.lock().unwrap();
+        if messages.len() == self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back(message.clone());
+    }
+}
+
+// collects the messages of a graph inside of a node (a loop body or a composite), for the node to hand to its own graph.
+// the inner node that logged a message is kept as a field
+pub(crate) struct ForwardSink(Arc<Mutex<Vec<DeltaMessage>>>);
+
+impl ForwardSink {
+    pub(crate) fn new(messages: Arc<Mutex<Vec<DeltaMessage>>>) -> ForwardSink {
+        ForwardSink(messages)
+    }
+}
+
+impl LogSink for ForwardSink {
+    fn log(&mut self, message: &DeltaMessage) {
+        let mut message = message.clone();
+        if let Some(node) = message.node.take() {
+            message = message.with_field("body_node", node);
+        }
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).push(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use delta_lib::{ DeltaMessage, LogLevel };
+    use crate::log::{ JsonLinesSink, LogSink, RingBufferSink };
+
+    #[test]
+    fn ring_buffer() {
+        let buffer = RingBufferSink::new(2);
+        let mut sink = buffer.clone();
+        for text in &["a", "b", "c"] {
+            sink.log(&DeltaMessage::new(LogLevel::Info, *text));
+        }
+        let texts: Vec<String> = buffer.messages().into_iter().map(|m| m.text).collect();
+        assert_eq!(vec!["b", "c"], texts);
+    }
+
+    #[test]
+    fn json_lines() {
+        let mut sink = JsonLinesSink::new(vec![]);
+        let mut message = DeltaMessage::new(LogLevel::Error, "say \"hi\"").with_field("x", 1);
+        message.node = Some(3);
+        message.tick = 7;
+        sink.log(&message);
+        sink.log(&DeltaMessage::new(LogLevel::Debug, "second"));
+
+        let out = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(2, lines.len());
+        assert_eq!(r#"{"fields":{"x":"1"},"level":"ERROR","node":3,"text":"say \"hi\"","tick":7}"#, lines[0]);
+    }
+}
@@ -0,0 +1,413 @@
+use std::any::Any;
+use std::sync::{ Arc, Mutex };
+use delta_lib::{ DeltaError, DeltaFuture, DeltaMessage, DynDeltaNode, ErrorKind, FieldSchema, Impulse, NodeSchema, PortDescriptor,
+    PortError, PortKind, ResetPolicy, SendNode, SetError, Value };
+use crate::graph::{ Graph, GraphError, NodeId };
+use crate::log::ForwardSink;
+
+enum LoopKind {
+    ForEach { item: (NodeId, usize) }, // each element of the `items` input is set on this body input in turn
+    While { condition: (NodeId, String) }, // a body output, the body runs again as long as it sends true
+    Repeat { count: usize },
+}
+
+// a node that runs a whole graph, its body, several times every time it executes.
+// the body is run like any graph (`Graph::run`), so nodes that are not ready or get a NOOP do not run in that iteration.
+// the body nodes are kept between iterations (and executions), so `#[delta_noreset]` fields carry state from one
+// iteration to the next. The result of an iteration can also be fed back into the body with `set_carry`.
+//
+// the edges of the body can not change once it is in a loop, so it is only ordered (and checked for cycles) once.
+//
+// ports: the `value` input (and `items` for a for-each) and the `out` and `iterations` outputs.
+// for-each sends the list of every result, the others the result of the last iteration that sent one
+pub struct Loop {
+    body: Graph,
+    order: Vec<NodeId>, // the order the body nodes run in
+    kind: LoopKind,
+    result: (NodeId, String), // the body output that is the result of an iteration
+    carry: Option<(NodeId, usize)>,
+    max_iterations: usize,
+
+    value: Option<Value>,
+    items: Option<Value>,
+    logs: Arc<Mutex<Vec<DeltaMessage>>>, // whatever the body logged, taken by the graph the loop is in
+}
+
+impl Loop {
+    // so a loop whose condition never turns false does not hang the graph
+    pub const DEFAULT_MAX_ITERATIONS: usize = 10_000;
+
+    // run the body once for every element of a list, the element is set on `item_input`
+    pub fn for_each(body: Graph, item: NodeId, item_input: &str, result: NodeId, result_output: &str) -> Result<Loop, GraphError> {
+        let item = (item, body.free_input(item, item_input)?);
+        Loop::new(body, LoopKind::ForEach { item }, result, result_output)
+    }
+
+    // run the body until `condition_output` sends anything but true. The body always runs at least once
+    pub fn while_loop(body: Graph, condition: NodeId, condition_output: &str, result: NodeId, result_output: &str) -> Result<Loop, GraphError> {
+        body.check_output(condition, condition_output)?;
+        Loop::new(body, LoopKind::While { condition: (condition, condition_output.to_owned()) }, result, result_output)
+    }
+
+    // run the body `count` times
+    pub fn repeat(body: Graph, count: usize, result: NodeId, result_output: &str) -> Result<Loop, GraphError> {
+        Loop::new(body, LoopKind::Repeat { count }, result, result_output)
+    }
+
+    fn new(mut body: Graph, kind: LoopKind, result: NodeId, result_output: &str) -> Result<Loop, GraphError> {
+        body.check_output(result, result_output)?;
+        let order = body.topological_order()?;
+        let logs = Arc::new(Mutex::new(vec![]));
+        body.set_log_sink(Box::new(ForwardSink::new(logs.clone())));
+
+        Ok(Loop {
+            body,
+            order,
+            kind,
+            result: (result, result_output.to_owned()),
+            carry: None,
+            max_iterations: Loop::DEFAULT_MAX_ITERATIONS,
+            value: None,
+            items: None,
+            logs,
+        })
+    }
+
+    // the loop's `value` input is set on `input` before the first iteration,
+    // after that the result of each iteration is set on it for the next one
+    pub fn set_carry(&mut self, node: NodeId, input: &str) -> Result<(), GraphError> {
+        self.carry = Some((node, self.body.free_input(node, input)?));
+        Ok(())
+    }
+
+    // running into the limit is an `ErrorKind::IterationLimit` error, for every kind of loop
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    // to look at the body nodes, e.g. the state they were left in
+    pub fn body(&self) -> &Graph {
+        &self.body
+    }
+
+    fn run(&mut self) -> Result<(Impulse<Value>, usize), DeltaError> {
+        let items = match self.kind {
+            LoopKind::ForEach { .. } => {
+                let items = self.items.take().unwrap_or_default();
+                items.cast::<Vec<Value>>().map_err(|found| DeltaError::new(ErrorKind::InvalidInput,
+                    format!("a for-each loop goes over a list, got a {}", found.kind())))?
+            },
+            _ => vec![],
+        };
+        if let Some((node, input)) = self.carry {
+            match self.value.take() {
+                Some(value) => self.body.bind(node, input, value),
+                None => self.body.unbind(node, input),
+            }
+        }
+
+        let mut iterations = 0;
+        let mut results = vec![];
+        let mut last = None;
+        loop {
+            match &self.kind {
+                LoopKind::ForEach { item: (node, input) } => match items.get(iterations) {
+                    Some(item) => self.body.bind(*node, *input, item.clone()),
+                    None => break,
+                },
+                LoopKind::Repeat { count } if iterations == *count => break,
+                _ => {},
+            }
+            if iterations == self.max_iterations {
+                return Err(DeltaError::new(ErrorKind::IterationLimit, format!("the loop was stopped after {} iterations", iterations)));
+            }
+
+            // inputs are only ever bound while the loop runs, so if they are all there for the first iteration they stay there
+            if iterations == 0 {
+                self.body.check_inputs().map_err(GraphError::into_delta_error)?;
+            }
+            self.body.run_in_order(&self.order).map_err(GraphError::into_delta_error)?;
+            iterations += 1;
+
+            let result = self.body.output_value(self.result.0, &self.result.1).cloned();
+            if let (Some((node, input)), Some(result)) = (self.carry, &result) {
+                self.body.bind(node, input, result.clone());
+            }
+
+            if let LoopKind::ForEach { .. } = self.kind {
+                results.extend(result);
+            } else if result.is_some() {
+                last = result;
+            }
+
+            if let LoopKind::While { condition: (node, output) } = &self.kind {
+                match self.body.output_value(*node, output).cloned().map(Value::cast::<bool>) {
+                    Some(Ok(true)) => {},
+                    Some(Err(found)) => return Err(DeltaError::new(ErrorKind::InvalidInput,
+                        format!("the loop condition has to be a bool, got a {}", found.kind()))),
+                    _ => break,
+                }
+            }
+        }
+
+        let out = match self.kind {
+            LoopKind::ForEach { .. } => Impulse::SEND(Value::List(results)),
+            _ => last.map_or(Impulse::NOOP, Impulse::SEND),
+        };
+        Ok((out, iterations))
+    }
+}
+
+fn port(name: &'static str, ty: &'static str, index: usize, kind: PortKind, required: bool, dynamic: bool) -> PortDescriptor {
+    PortDescriptor { name: name.into(), ty, index, kind, required, dynamic, error: false }
+}
+
+fn field(port: &PortDescriptor, doc: &'static str) -> FieldSchema {
+    FieldSchema { name: port.name.clone(), ty: port.ty, default: None, reset: ResetPolicy::Reset, public: false, required: port.required, doc }
+}
+
+impl DynDeltaNode for Loop {
+    fn __type_name(&self) -> &str {
+        "delta_core::Loop"
+    }
+
+    fn __port_list(&self) -> Vec<PortDescriptor> {
+        let value = std::any::type_name::<Value>();
+        let mut ports = vec![port("value", value, 0, PortKind::Input, false, true)];
+        if let LoopKind::ForEach { .. } = self.kind {
+            ports.push(port("items", value, 1, PortKind::Input, true, true));
+        }
+        ports.push(port("out", value, 0, PortKind::Output, false, true));
+        ports.push(port("iterations", std::any::type_name::<usize>(), 1, PortKind::Output, false, false));
+        ports
+    }
+
+    fn __describe_dyn(&self) -> NodeSchema {
+        let ports = self.__port_list();
+        let doc = |name: &str| match name {
+            "value" => "Set on the carried input before the first iteration",
+            "items" => "The list to go over",
+            "out" if ports.iter().any(|p| p.name == "items") => "The result of every iteration that sent one",
+            "out" => "The result of the last iteration that sent one",
+            _ => "How many times the body ran",
+        };
+
+        NodeSchema {
+            name: "delta_core::Loop".into(),
+            doc: match self.kind {
+                LoopKind::ForEach { .. } => "Runs its body for every element of a list.",
+                LoopKind::While { .. } => "Runs its body until the condition is no longer true.",
+                LoopKind::Repeat { .. } => "Runs its body a fixed number of times.",
+            }.into(),
+            inputs: ports.iter().filter(|p| p.kind == PortKind::Input).map(|p| field(p, doc(&p.name))).collect(),
+            outputs: ports.iter().filter(|p| p.kind == PortKind::Output).map(|p| field(p, doc(&p.name))).collect(),
+            state: vec![],
+        }
+    }
+
+    fn __set_input(&mut self, index: usize, value: &Value) -> Result<(), PortError> {
+        match (index, &self.kind) {
+            (0, _) => self.value = Some(value.clone()),
+            (1, LoopKind::ForEach { .. }) => self.items = Some(value.clone()),
+            _ => return Err(PortError::UnknownPort(index)),
+        }
+        Ok(())
+    }
+
+    fn __unset_input(&mut self, index: usize) {
+        match index {
+            0 => self.value = None,
+            1 => self.items = None,
+            _ => {},
+        }
+    }
+
+    fn __set_by_name_dyn(&mut self, field: &str, value: Value) -> Result<(), SetError> {
+        match self.__port_list().iter().find(|p| p.kind == PortKind::Input && p.name == field) {
+            Some(port) => match self.__set_input(port.index, &value) {
+                Ok(()) => Ok(()),
+                Err(PortError::TypeMismatch { port, expected, .. }) => Err(SetError::TypeMismatch { field: port, expected, found: value }),
+                Err(PortError::UnknownPort(_)) => Err(SetError::UnknownField(field.to_owned())),
+            },
+            None => Err(SetError::UnknownField(field.to_owned())),
+        }
+    }
+
+    fn __ready(&self) -> bool {
+        match self.kind {
+            LoopKind::ForEach { .. } => self.items.is_some(),
+            _ => true,
+        }
+    }
+
+    // the body keeps its own clock, it is not clocked
+    fn __set_clock(&mut self, _tick: u64) {}
+
+    fn __drain_logs(&mut self) -> Vec<DeltaMessage> {
+        std::mem::take(&mut *self.logs.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn __execute_dyn(&mut self) -> Vec<Impulse<Value>> {
+        let outputs = match self.run() {
+            Ok((out, iterations)) => vec![out, Impulse::SEND(Value::from(iterations))],
+            Err(error) => vec![Impulse::ERROR(error), Impulse::NOOP],
+        };
+        self.value = None;
+        self.items = None;
+        outputs
+    }
+
+    fn __execute_dyn_async(&mut self) -> DeltaFuture<'_, Vec<Impulse<Value>>> {
+        Box::pin(async move { self.__execute_dyn() })
+    }
+
+    // the body can hold thread local nodes
+    fn __as_send(&mut self) -> Option<&mut dyn SendNode> {
+        None
+    }
+
+    fn __as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn __as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use delta_lib::{ DeltaNode, DynDeltaNode, ErrorKind, Impulse, Value };
+    use delta_lib_macro::{ delta_node_struct, delta_node_impl, RegisterDeltaNode };
+    use crate::graph::{ Graph, GraphError };
+    use crate::loops::Loop;
+
+    // adds up everything it is sent
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Sum {
+        x: i64,
+
+        #[delta_noreset]
+        #[delta_default(0)]
+        total: i64,
+    }
+
+    #[delta_node_impl( on_exec = "add" )]
+    impl Sum {
+        fn add(&mut self) -> Impulse<i64> {
+            self.total += self.x;
+            Impulse::SEND(self.total)
+        }
+    }
+
+    // x / 2, only for even numbers
+    #[delta_node_struct]
+    #[derive(RegisterDeltaNode)]
+    struct Halve {
+        x: i64,
+
+        #[delta_output]
+        more: Impulse<bool>, // whether the result can be halved again
+    }
+
+    #[delta_node_impl( on_exec = "halve" )]
+    impl Halve {
+        fn halve(&mut self) -> Impulse<i64> {
+            if self.x % 2 != 0 {
+                return Impulse::NOOP;
+            }
+            self.more = Impulse::SEND(self.x / 2 % 2 == 0);
+            if self.x == 42 {
+                self.__log(delta_lib::LogLevel::Info, "the answer");
+            }
+            Impulse::SEND(self.x / 2)
+        }
+    }
+
+    #[test]
+    fn for_each() {
+        let mut body = Graph::new();
+        let halve = body.add_node(Halve::__initialize());
+        let sum = body.add_node(Sum::__initialize());
+        body.connect(halve, "out", sum, "x").unwrap();
+        let mut each = Loop::for_each(body, halve, "x", halve, "out").unwrap();
+
+        // odd numbers send NOOP, so they are left out of the results and do not reach the sum
+        each.__set_by_name_dyn("items", Value::List(vec![Value::Int(4), Value::Int(3), Value::Int(42)])).unwrap();
+        assert_eq!(vec![Impulse::SEND(Value::List(vec![Value::Int(2), Value::Int(21)])), Impulse::SEND(Value::Int(3))], each.__execute_dyn());
+        assert_eq!(Some(23), each.body().output::<i64>(sum, "out"));
+
+        let logs = each.__drain_logs();
+        assert_eq!(vec![("body_node".to_owned(), "0".to_owned())], logs[0].fields);
+        assert!(each.__drain_logs().is_empty());
+
+        // the sum keeps its total between executions too
+        each.__set_by_name_dyn("items", Value::Bytes(vec![2])).unwrap();
+        each.__execute_dyn();
+        assert_eq!(Some(24), each.body().output::<i64>(sum, "out"));
+
+        assert!(!each.__ready());
+        each.__set_by_name_dyn("items", Value::Int(1)).unwrap();
+        assert!(matches!(&each.__execute_dyn()[0], Impulse::ERROR(e) if e.kind == ErrorKind::InvalidInput));
+
+        let body = Graph::new();
+        assert!(matches!(Loop::for_each(body, halve, "x", halve, "out"), Err(GraphError::UnknownNode(_))));
+    }
+
+    #[test]
+    fn while_loop() {
+        // halve until the number is odd, carrying the result into the next iteration
+        let mut body = Graph::new();
+        let halve = body.add_node(Halve::__initialize());
+        let mut halving = Loop::while_loop(body, halve, "more", halve, "out").unwrap();
+        halving.set_carry(halve, "x").unwrap();
+
+        halving.__set_by_name_dyn("value", Value::Int(96)).unwrap();
+        assert_eq!(vec![Impulse::SEND(Value::Int(3)), Impulse::SEND(Value::Int(5))], halving.__execute_dyn());
+
+        // the carried input is only set by the loop, without a value the body never gets an input
+        assert!(matches!(&halving.__execute_dyn()[0], Impulse::ERROR(e) if e.kind == ErrorKind::Other));
+
+        // the safeguard stops a loop that would never end
+        halving.set_max_iterations(3);
+        halving.__set_by_name_dyn("value", Value::Int(0)).unwrap();
+        assert!(matches!(&halving.__execute_dyn()[0], Impulse::ERROR(e) if e.kind == ErrorKind::IterationLimit));
+    }
+
+    #[test]
+    fn repeat() {
+        let mut body = Graph::new();
+        let sum = body.add_node(Sum::__initialize());
+        body.set_input(sum, "x", 5_i64).unwrap();
+        let mut five = Loop::repeat(body, 4, sum, "out").unwrap();
+        assert_eq!(vec![Impulse::SEND(Value::Int(20)), Impulse::SEND(Value::Int(4))], five.__execute_dyn());
+
+        // a loop is a node like any other
+        let mut graph = Graph::new();
+        let looped = graph.add_node(Box::new(five));
+        let after = graph.add_node(Sum::__initialize());
+        graph.connect(looped, "out", after, "x").unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(40), graph.output::<i64>(after, "out"));
+        assert_eq!(Some(4), graph.output::<usize>(looped, "iterations"));
+        let ports = graph.ports(looped).unwrap();
+        assert!(ports.iter().all(|p| p.dynamic == (p.name != "iterations")));
+        assert_eq!(std::any::type_name::<usize>(), ports.iter().find(|p| p.name == "iterations").unwrap().ty);
+
+        let mut body = Graph::new();
+        let sum = body.add_node(Sum::__initialize());
+        body.set_input(sum, "x", 1_i64).unwrap();
+        let mut many = Loop::repeat(body, 11, sum, "out").unwrap();
+        many.set_max_iterations(10);
+        assert!(matches!(many.__set_by_name_dyn("items", Value::Int(1)), Err(delta_lib::SetError::UnknownField(f)) if f == "items"));
+        assert!(matches!(&many.__execute_dyn()[0], Impulse::ERROR(e) if e.kind == ErrorKind::IterationLimit));
+
+        // the body is ordered when the loop is made, not every iteration
+        let mut body = Graph::new();
+        let (a, b) = (body.add_node(Sum::__initialize()), body.add_node(Sum::__initialize()));
+        body.connect(a, "out", b, "x").unwrap();
+        body.connect(b, "out", a, "x").unwrap();
+        assert!(matches!(Loop::repeat(body, 2, a, "out"), Err(GraphError::Cycle(_))));
+    }
+}
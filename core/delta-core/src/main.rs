@@ -1,14 +1,31 @@
-use delta_lib::{ DeltaNode, Impulse };
-use arithmetic::Addi32;
+use delta_core::{ cli, PluginLoader };
 
 fn main() {
-    println!("Hello, world!");
-    let mut adder: Box<Addi32> = Addi32::__initialize();
-    adder.__set_x(2);
-    adder.__set_y(2);
-    println!("2 plus 2 is {}", match adder.__execute() {
-        Impulse::<i32>::SEND(x) => x.to_string(),
-        Impulse::NOOP => "ERROR: NOOP".to_owned(),
-        Impulse::TICK => "ERROR: TICK".to_owned(),
-    });
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        },
+    };
+
+    // node packs dropped into ./plugins are loaded at startup
+    let mut plugins = PluginLoader::new();
+    if std::path::Path::new("plugins").is_dir() {
+        match plugins.load_dir("plugins") {
+            Ok(failed) => {
+                for (_, e) in failed {
+                    eprintln!("warning: {}", e);
+                }
+            },
+            Err(e) => eprintln!("warning: {}", e),
+        }
+    }
+
+    // the built in nodes can be created by name, next to the ones from plugins
+    let mut registry = plugins.into_registry();
+    cli::register_builtin(&mut registry);
+
+    let code = cli::execute(&command, &registry, &mut std::io::stdout().lock());
+    std::process::exit(code);
 }
@@ -0,0 +1,205 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::{ Path, PathBuf };
+use delta_lib::{ NodeRegistry, PLUGIN_ABI_VERSION, PLUGIN_BUILD_INFO };
+
+#[derive(Debug)]
+pub enum PluginError {
+    Io { path: PathBuf, error: std::io::Error },
+    Load { path: PathBuf, error: String }, // not a library the OS can open
+    MissingSymbol { path: PathBuf, symbol: &'static str }, // a library, but not a delta plugin
+    AbiMismatch { path: PathBuf, expected: u32, found: u32 },
+    BuildMismatch { path: PathBuf, expected: String, found: String }, // different compiler or delta-lib version
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Io { path, error } => write!(f, "could not read {}: {}", path.display(), error),
+            PluginError::Load { path, error } => write!(f, "could not load {}: {}", path.display(), error),
+            PluginError::MissingSymbol { path, symbol } => write!(f, "{} is not a delta plugin, `{}` is missing", path.display(), symbol),
+            PluginError::AbiMismatch { path, expected, found } => write!(f, "{} uses plugin ABI {}, expected {}", path.display(), found, expected),
+            PluginError::BuildMismatch { path, expected, found } => write!(f, "{} was built with `{}`, expected `{}`", path.display(), found, expected),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+// opens node crates built with `delta_plugin!` at runtime, and registers their node types.
+// plugins are never unloaded, the nodes created from them can outlive the loader
+#[derive(Default)]
+pub struct PluginLoader {
+    registry: NodeRegistry,
+}
+
+impl PluginLoader {
+    pub fn new() -> PluginLoader {
+        PluginLoader::default()
+    }
+
+    // load every library in a directory (`.so`, `.dylib` or `.dll` depending on the platform).
+    // each library is loaded on its own, one that fails does not stop the rest. Returns every path that could not be loaded
+    // with its error, only failing as a whole if the directory can not be read
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<(PathBuf, PluginError)>, PluginError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir).map_err(|error| PluginError::Io { path: dir.to_owned(), error })?;
+
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry.map_err(|error| PluginError::Io { path: dir.to_owned(), error })?.path();
+            if path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION) {
+                paths.push(path);
+            }
+        }
+        paths.sort(); // same order on every platform
+
+        let mut failed = vec![];
+        for path in paths {
+            if let Err(error) = self.load(&path) {
+                failed.push((path, error));
+            }
+        }
+        Ok(failed)
+    }
+
+    // load a single plugin, returns how many node types it registered
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, PluginError> {
+        self.load_checked(path.as_ref(), PLUGIN_ABI_VERSION, PLUGIN_BUILD_INFO)
+    }
+
+    // `load` against any ABI version and build info, so the checks can be tested with a real plugin
+    fn load_checked(&mut self, path: &Path, abi: u32, build: &str) -> Result<usize, PluginError> {
+
+        // loading a library runs its initializers, there is no way to check it is safe beforehand
+        let library = unsafe { libloading::Library::new(path) }.map_err(|error| PluginError::Load { path: path.to_owned(), error: error.to_string() })?;
+
+        // the first two only use the C ABI, so they can be called no matter how the plugin was built
+        let abi_version = unsafe { symbol::<extern "C" fn() -> u32>(&library, path, "__delta_plugin_abi_version")?() };
+        if abi_version != abi {
+            return Err(PluginError::AbiMismatch { path: path.to_owned(), expected: abi, found: abi_version });
+        }
+
+        let build_info = unsafe { CStr::from_ptr(symbol::<extern "C" fn() -> *const c_char>(&library, path, "__delta_plugin_build_info")?()) };
+        let expected = build.trim_end_matches('\0');
+        if build_info.to_bytes() != expected.as_bytes() {
+            return Err(PluginError::BuildMismatch { path: path.to_owned(), expected: expected.to_owned(), found: build_info.to_string_lossy().into_owned() });
+        }
+
+        // same compiler and delta-lib, so the rust types line up
+        let mut registry = NodeRegistry::new();
+        unsafe { symbol::<fn(&mut NodeRegistry)>(&library, path, "__delta_plugin_register")?(&mut registry) };
+
+        // the nodes' code lives in the library, so it has to stay loaded
+        std::mem::forget(library);
+
+        let count = registry.len();
+        self.registry.extend(registry);
+        Ok(count)
+    }
+
+    // every node type from every plugin loaded so far
+    pub fn registry(&self) -> &NodeRegistry {
+        &self.registry
+    }
+
+    pub fn into_registry(self) -> NodeRegistry {
+        self.registry
+    }
+}
+
+// the function behind a symbol, copied out so it does not borrow the library
+unsafe fn symbol<T: Copy>(library: &libloading::Library, path: &Path, name: &'static str) -> Result<T, PluginError> {
+    library.get::<T>(name.as_bytes()).map(|s| *s).map_err(|_| PluginError::MissingSymbol { path: path.to_owned(), symbol: name })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use delta_lib::{ NodeRegistry, PLUGIN_ABI_VERSION, PLUGIN_BUILD_INFO };
+    use crate::graph::Graph;
+    use crate::plugin::{ PluginError, PluginLoader };
+
+    // builds the arithmetic node crate the way it is shipped as a plugin, returns the path of the library
+    fn arithmetic_plugin() -> PathBuf {
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/../../nodes/arithmetic/Cargo.toml");
+        let output = std::process::Command::new(env!("CARGO"))
+            .args(["build", "--lib", "--message-format=json", "--manifest-path", manifest])
+            .output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        String::from_utf8(output.stdout).unwrap().lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|message| message["reason"] == "compiler-artifact" && message["target"]["name"] == "arithmetic")
+            .flat_map(|message| message["filenames"].as_array().cloned().unwrap_or_default())
+            .filter_map(|file| file.as_str().map(PathBuf::from))
+            .find(|file| file.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+            .unwrap()
+    }
+
+    // builds the arithmetic crate with cargo, run it with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn load_plugin() {
+        let path = arithmetic_plugin();
+
+        // a plugin from an older or newer engine is refused before anything of it runs
+        let mut loader = PluginLoader::new();
+        assert!(matches!(loader.load_checked(&path, PLUGIN_ABI_VERSION + 1, PLUGIN_BUILD_INFO),
+            Err(PluginError::AbiMismatch { expected, found, .. }) if expected == PLUGIN_ABI_VERSION + 1 && found == PLUGIN_ABI_VERSION));
+        assert!(matches!(loader.load_checked(&path, PLUGIN_ABI_VERSION, "rustc 0.0.0; delta-lib 0.0.0\0"),
+            Err(PluginError::BuildMismatch { found, .. }) if format!("{}\0", found) == PLUGIN_BUILD_INFO));
+        assert!(loader.registry().is_empty());
+
+        // the plugin registers the same nodes as linking the crate does
+        let count = loader.load(&path).unwrap();
+        let mut linked = NodeRegistry::new();
+        arithmetic::__delta_plugin_register(&mut linked);
+        assert_eq!(linked.len(), count);
+        assert_eq!(linked.nodes().map(|n| &n.name).collect::<Vec<_>>(), loader.registry().nodes().map(|n| &n.name).collect::<Vec<_>>());
+
+        // and its nodes run like any other
+        let mut graph = Graph::new();
+        let sub = graph.add_node(loader.registry().create("arithmetic::numeric::Subi64").unwrap());
+        graph.set_input(sub, "x", 5_i64).unwrap();
+        graph.set_input(sub, "y", 7_i64).unwrap();
+        graph.run().unwrap();
+        assert_eq!(Some(-2_i64), graph.output::<i64>(sub, "out"));
+    }
+
+    // with their `plugin` feature off, node crates can be linked into one binary without their exports clashing
+    #[test]
+    fn linked_packs() {
+        let mut registry = NodeRegistry::new();
+        arithmetic::__delta_plugin_register(&mut registry);
+        logic::__delta_plugin_register(&mut registry);
+        assert!(registry.contains("arithmetic::Addi32"));
+        assert!(registry.contains("logic::And"));
+        assert_eq!(PLUGIN_ABI_VERSION, arithmetic::__delta_plugin_abi_version());
+        assert_eq!(PLUGIN_ABI_VERSION, logic::__delta_plugin_abi_version());
+    }
+
+    #[test]
+    fn load_errors() {
+        let dir = std::env::temp_dir().join(format!("delta-plugins-{}", std::process::id()));
+        let mut loader = PluginLoader::new();
+        assert!(matches!(loader.load_dir(&dir), Err(PluginError::Io { .. })));
+
+        // only files with the library extension are picked up
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("readme.txt"), "not a plugin").unwrap();
+        assert!(loader.load_dir(&dir).unwrap().is_empty());
+
+        // every library fails on its own, with its own path
+        let fakes: Vec<PathBuf> = ["a", "b"].iter().map(|name| dir.join(format!("{}.{}", name, std::env::consts::DLL_EXTENSION))).collect();
+        for fake in fakes.iter() {
+            std::fs::write(fake, "not a plugin either").unwrap();
+        }
+        let failed = loader.load_dir(&dir).unwrap();
+        assert_eq!(fakes, failed.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>());
+        assert!(failed.iter().all(|(path, error)| matches!(error, PluginError::Load { path: p, .. } if p == path)));
+        assert!(loader.registry().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
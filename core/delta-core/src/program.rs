@@ -0,0 +1,471 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::path::Path;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value as Json;
+use delta_lib::{ DynDeltaNode, NodeRegistry, PortError, PortKind, RegistryError, Value };
+use crate::composite::Composite;
+use crate::graph::{ Graph, GraphError, NodeId };
+use crate::loops::Loop;
+
+// a delta program is a graph saved as JSON:
+//
+// {
+//     "format": "delta-program",   // always this, so other JSON files are not mistaken for programs
+//     "version": 1,                // `PROGRAM_FORMAT_VERSION` when it was saved, newer versions are refused
+//     "metadata": { "name": "adder", "description": "(2 + 2) + 3" },
+//     "nodes": [
+//         // `id` names the node inside of the program, `type` is the name its node type is registered under
+//         // and `inputs` gives inputs a fixed value, any JSON value that converts to the type of the input (see `Value`)
+//         { "id": "first", "type": "arithmetic::Addi32", "inputs": { "x": 2, "y": 2 } },
+//         { "id": "second", "type": "arithmetic::Addi32", "inputs": { "y": 3 } }
+//     ],
+//     "edges": [
+//         { "from": "first", "output": "out", "to": "second", "input": "x" }
+//     ]
+// }
+//
+// `metadata`, `inputs` and `edges` can be left out. A tensor is written as { "shape": [2, 2], "data": [1, 2, 3, 4] }.
+// Error policies and thread counts are settings of whoever runs
+// the program, so they are not saved
+//
+// since version 2 a program can also be used as a node (see `Composite`). The ports of the program are inner ports,
+// given a name of their own:
+//
+//     "inputs": [{ "name": "a", "node": "first", "port": "x" }],
+//     "outputs": [{ "name": "sum", "node": "second", "port": "out" }]
+//
+// and node types can be defined inside of a program, as nested programs with a `type` (and no format or version).
+// they can be used by the nodes of the program and of the composites after them, before any registered node type:
+//
+//     "composites": [{ "type": "mylib::AddThree", "nodes": [...], "edges": [...], "inputs": [...], "outputs": [...] }]
+pub const PROGRAM_FORMAT: &str = "delta-program";
+pub const PROGRAM_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum ProgramError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    NotAProgram(String), // the `format` field was something else
+    UnsupportedVersion(u32),
+    DuplicateNode(String),
+    UnknownNode(String), // an edge or port uses an id that is not in `nodes`
+    DuplicatePort(String),
+    Registry(RegistryError),
+    Composite { ty: String, error: Box<ProgramError> }, // building a composite inside of the program failed
+    RecursiveComposite(String), // a composite that (maybe through other composites) contains itself
+    Graph { node: String, error: GraphError },
+    UnsupportedNode { node: String, ty: String }, // a node that has no form in a program, like a `Loop`
+    UnsupportedType { node: String, input: String, ty: &'static str }, // the input holds an opaque value, which has no JSON form
+    InvalidValue { node: String, input: String, ty: &'static str, value: Json },
+}
+
+impl std::fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramError::Io(error) => write!(f, "{}", error),
+            ProgramError::Json(error) => write!(f, "invalid program: {}", error),
+            ProgramError::NotAProgram(format) => write!(f, "expected a `{}` file, found `{}`", PROGRAM_FORMAT, format),
+            ProgramError::UnsupportedVersion(version) => write!(f, "program version {} is newer than the supported version {}", version, PROGRAM_FORMAT_VERSION),
+            ProgramError::DuplicateNode(id) => write!(f, "there is more than one node with the id `{}`", id),
+            ProgramError::UnknownNode(id) => write!(f, "there is no node with the id `{}`", id),
+            ProgramError::DuplicatePort(name) => write!(f, "there is more than one port named `{}`", name),
+            ProgramError::Registry(error) => write!(f, "{}", error),
+            ProgramError::Composite { ty, error } => write!(f, "composite `{}`: {}", ty, error),
+            ProgramError::RecursiveComposite(ty) => write!(f, "composite `{}` contains itself", ty),
+            ProgramError::Graph { node, error } => write!(f, "node `{}`: {}", node, error),
+            ProgramError::UnsupportedNode { node, ty } => write!(f, "node `{}`: `{}` nodes can not be saved in a program", node, ty),
+            ProgramError::UnsupportedType { node, input, ty } => write!(f, "node `{}` input `{}`: values of type `{}` can not be saved in a program", node, input, ty),
+            ProgramError::InvalidValue { node, input, ty, value } => write!(f, "node `{}` input `{}`: {} is not a valid `{}`", node, input, value, ty),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+impl From<std::io::Error> for ProgramError {
+    fn from(error: std::io::Error) -> ProgramError {
+        ProgramError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ProgramError {
+    fn from(error: serde_json::Error) -> ProgramError {
+        ProgramError::Json(error)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProgramMetadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramNode {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, Json>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramEdge {
+    pub from: String,
+    pub output: String,
+    pub to: String,
+    pub input: String,
+}
+
+// an inner port that is a port of the program itself, when it is used as a composite
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramPort {
+    pub name: String,
+    pub node: String,
+    pub port: String,
+}
+
+// a node type defined by a nested program
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompositeDefinition {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(flatten)]
+    pub program: Program,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    #[serde(default)]
+    pub metadata: ProgramMetadata,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub composites: Vec<CompositeDefinition>,
+    pub nodes: Vec<ProgramNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<ProgramEdge>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<ProgramPort>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<ProgramPort>,
+}
+
+// what is actually in the file, the format and version are only checked when loading
+#[derive(Serialize, Deserialize)]
+struct ProgramFile<P> {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    program: P,
+}
+
+// a program turned into a graph, along with which node each id became
+pub struct LoadedProgram {
+    pub graph: Graph,
+    pub nodes: BTreeMap<String, NodeId>,
+    pub metadata: ProgramMetadata,
+    pub inputs: Vec<ProgramPort>,
+    pub outputs: Vec<ProgramPort>,
+}
+
+impl LoadedProgram {
+    // the program as it is now, e.g. after changing some inputs
+    pub fn to_program(&self) -> Result<Program, ProgramError> {
+        let mut program = Program::from_graph(&self.graph, &self.nodes)?;
+        program.metadata = self.metadata.clone();
+        program.inputs = self.inputs.clone();
+        program.outputs = self.outputs.clone();
+        Ok(program)
+    }
+}
+
+impl Program {
+    pub fn from_json(json: &str) -> Result<Program, ProgramError> {
+        // check the header first, so a newer program fails with a version error instead of some missing field
+        let header: ProgramFile<serde::de::IgnoredAny> = serde_json::from_str(json)?;
+        if header.format != PROGRAM_FORMAT {
+            return Err(ProgramError::NotAProgram(header.format));
+        }
+        if header.version > PROGRAM_FORMAT_VERSION {
+            return Err(ProgramError::UnsupportedVersion(header.version));
+        }
+
+        let file: ProgramFile<Program> = serde_json::from_str(json)?;
+        Ok(file.program)
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = ProgramFile { format: PROGRAM_FORMAT.to_owned(), version: PROGRAM_FORMAT_VERSION, program: self };
+        serde_json::to_string_pretty(&file).expect("programs only hold strings and JSON values")
+    }
+
+    // create every node from the composites of the program or the registry, then set the inputs and connect the edges
+    pub fn build(&self, registry: &NodeRegistry) -> Result<LoadedProgram, ProgramError> {
+        self.build_in(registry, &[], &mut vec![])
+    }
+
+    // `outer` are the composites the program can use besides its own, `building` the composites it is inside of
+    fn build_in(&self, registry: &NodeRegistry, outer: &[&CompositeDefinition], building: &mut Vec<String>) -> Result<LoadedProgram, ProgramError> {
+        let mut graph = Graph::new();
+        let mut nodes = BTreeMap::new();
+
+        // a composite can use the ones defined before it, its own composites come first
+        let mut scope: Vec<&CompositeDefinition> = outer.to_vec();
+        let mut create = |ty: &str, scope: &[&CompositeDefinition]| -> Result<Box<dyn DynDeltaNode>, ProgramError> {
+            let definition = match scope.iter().rev().find(|c| c.ty == ty) {
+                Some(definition) => definition,
+                None => return registry.create(ty).map_err(ProgramError::Registry),
+            };
+            if building.iter().any(|b| *b == ty) {
+                return Err(ProgramError::RecursiveComposite(ty.to_owned()));
+            }
+
+            building.push(ty.to_owned());
+            let composite = definition.program.build_in(registry, scope, building)
+                .and_then(|loaded| Composite::from_program(ty, loaded))
+                .map_err(|error| match error {
+                    error @ ProgramError::RecursiveComposite(_) => error,
+                    error => ProgramError::Composite { ty: ty.to_owned(), error: Box::new(error) },
+                });
+            building.pop();
+            Ok(Box::new(composite?))
+        };
+
+        for definition in self.composites.iter() {
+            // build each one once, so a broken composite fails even if nothing uses it
+            scope.push(definition);
+            create(&definition.ty, &scope)?;
+        }
+
+        for node in self.nodes.iter() {
+            if nodes.contains_key(&node.id) {
+                return Err(ProgramError::DuplicateNode(node.id.clone()));
+            }
+            let id = graph.add_node(create(&node.ty, &scope)?);
+            nodes.insert(node.id.clone(), id);
+
+            for (input, json) in node.inputs.iter() {
+                match graph.set_input_value(id, input, json_to_value(json)) {
+                    Ok(()) => {},
+                    Err(GraphError::Port { error: PortError::TypeMismatch { expected, .. }, .. }) =>
+                        return Err(ProgramError::InvalidValue { node: node.id.clone(), input: input.clone(), ty: expected, value: json.clone() }),
+                    Err(error) => return Err(ProgramError::Graph { node: node.id.clone(), error }),
+                }
+            }
+        }
+
+        for edge in self.edges.iter() {
+            let from = *nodes.get(&edge.from).ok_or_else(|| ProgramError::UnknownNode(edge.from.clone()))?;
+            let to = *nodes.get(&edge.to).ok_or_else(|| ProgramError::UnknownNode(edge.to.clone()))?;
+            graph.connect(from, &edge.output, to, &edge.input).map_err(|error| ProgramError::Graph { node: edge.to.clone(), error })?;
+        }
+
+        for port in self.inputs.iter().chain(self.outputs.iter()) {
+            if !nodes.contains_key(&port.node) {
+                return Err(ProgramError::UnknownNode(port.node.clone()));
+            }
+        }
+
+        Ok(LoadedProgram { graph, nodes, metadata: self.metadata.clone(), inputs: self.inputs.clone(), outputs: self.outputs.clone() })
+    }
+
+    // save a graph, `names` gives nodes their id in the program. Nodes without a name are called `node<index>`.
+    // every composite in the graph is saved along with it, so the program can be loaded without registering them
+    pub fn from_graph(graph: &Graph, names: &BTreeMap<String, NodeId>) -> Result<Program, ProgramError> {
+        // the id of every node in the program. Nodes without a name get `node{index}`, with a suffix if a name already uses it
+        let mut ids: Vec<Option<String>> = vec![None; graph.len()];
+        for (name, id) in names.iter() {
+            if let Some(slot @ None) = ids.get_mut(id.0) {
+                *slot = Some(name.clone());
+            }
+        }
+        let mut taken: BTreeSet<String> = names.keys().cloned().collect();
+        let ids: Vec<String> = ids.into_iter().enumerate().map(|(i, name)| name.unwrap_or_else(|| {
+            let mut id = format!("node{}", i);
+            let mut suffix = 1;
+            while taken.contains(&id) {
+                id = format!("node{}_{}", i, suffix);
+                suffix += 1;
+            }
+            taken.insert(id.clone());
+            id
+        })).collect();
+        let node_name = |id: NodeId| ids[id.0].clone();
+        let graph_error = |id: NodeId, error| ProgramError::Graph { node: node_name(id), error };
+
+        let mut nodes = vec![];
+        let mut composites: Vec<CompositeDefinition> = vec![];
+        for i in 0..graph.len() {
+            let id = NodeId(i);
+            let ty = graph.node_type(id).map_err(|e| graph_error(id, e))?.to_owned();
+            // loops are only built in code, there is nothing a program could load them from
+            if graph.node::<Loop>(id).is_some() {
+                return Err(ProgramError::UnsupportedNode { node: node_name(id), ty });
+            }
+            if let Some(composite) = graph.node::<Composite>(id) {
+                if !composites.iter().any(|c| c.ty == ty) {
+                    composites.push(CompositeDefinition { ty: ty.clone(), program: composite.to_program()? });
+                }
+            }
+            nodes.push(ProgramNode { id: node_name(id), ty, inputs: BTreeMap::new() });
+        }
+
+        for (id, index, value) in graph.bindings() {
+            let port = graph.ports(id).map_err(|e| graph_error(id, e))?.iter()
+                .find(|p| p.kind == PortKind::Input && p.index == index)
+                .ok_or_else(|| graph_error(id, GraphError::UnknownPort { node: id, port: index.to_string() }))?;
+            let json = value_to_json(value).ok_or_else(|| ProgramError::UnsupportedType { node: node_name(id), input: port.name.to_string(), ty: port.ty })?;
+            nodes[id.0].inputs.insert(port.name.to_string(), json);
+        }
+
+        let mut edges = vec![];
+        for edge in graph.edges() {
+            let port_name = |id: NodeId, index: usize, kind: PortKind| -> Result<String, ProgramError> {
+                graph.ports(id).map_err(|e| graph_error(id, e))?.iter()
+                    .find(|p| p.kind == kind && p.index == index)
+                    .map(|p| p.name.to_string())
+                    .ok_or_else(|| graph_error(id, GraphError::UnknownPort { node: id, port: index.to_string() }))
+            };
+            edges.push(ProgramEdge {
+                from: node_name(edge.from),
+                output: port_name(edge.from, edge.output, PortKind::Output)?,
+                to: node_name(edge.to),
+                input: port_name(edge.to, edge.input, PortKind::Input)?,
+            });
+        }
+
+        Ok(Program { metadata: ProgramMetadata::default(), composites, nodes, edges, inputs: vec![], outputs: vec![] })
+    }
+}
+
+pub fn load_program<P: AsRef<Path>>(path: P, registry: &NodeRegistry) -> Result<LoadedProgram, ProgramError> {
+    Program::from_json(&std::fs::read_to_string(path)?)?.build(registry)
+}
+
+pub fn save_program<P: AsRef<Path>>(path: P, program: &Program) -> Result<(), ProgramError> {
+    std::fs::write(path, program.to_json() + "\n")?;
+    Ok(())
+}
+
+// JSON numbers become an `Int` if they are whole and fit in an `i64`, null is the unit value
+fn json_to_value(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Unit,
+        Json::Bool(x) => Value::Bool(*x),
+        Json::Number(x) => match (x.as_i64(), x.as_u64()) {
+            (Some(x), _) => Value::Int(x),
+            (None, Some(x)) => Value::UInt(x),
+            (None, None) => Value::Float(x.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(x) => Value::String(x.clone()),
+        Json::Array(items) => Value::List(items.iter().map(json_to_value).collect()),
+        Json::Object(map) => Value::Map(map.iter().map(|(k, v)| (k.clone(), json_to_value(v))).collect()),
+    }
+}
+
+// None for opaque values, they have no JSON form
+pub(crate) fn value_to_json(value: &Value) -> Option<Json> {
+    Some(match value {
+        Value::Unit => Json::Null,
+        Value::Bool(x) => Json::from(*x),
+        Value::Int(x) => Json::from(*x),
+        Value::UInt(x) => Json::from(*x),
+        Value::Float(x) => Json::from(*x),
+        Value::String(x) => Json::from(x.as_str()),
+        Value::Bytes(x) => Json::from(x.clone()),
+        Value::List(items) => Json::Array(items.iter().map(value_to_json).collect::<Option<_>>()?),
+        Value::Map(map) => Json::Object(map.iter().map(|(k, v)| value_to_json(v).map(|v| (k.clone(), v))).collect::<Option<_>>()?),
+        Value::Tensor(x) => serde_json::json!({ "shape": x.shape(), "data": x.data() }),
+        Value::Opaque(_) => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use delta_lib::{ DeltaNode, NodeRegistry, RegistryError };
+    use arithmetic::Addi32;
+    use crate::graph::NodeId;
+    use crate::program::{ load_program, save_program, Program, ProgramError };
+
+    const ADDER: &str = r#"{
+        "format": "delta-program",
+        "version": 1,
+        "metadata": { "name": "adder", "description": "(2 + 2) + 3" },
+        "nodes": [
+            { "id": "first", "type": "arithmetic::Addi32", "inputs": { "x": 2, "y": 2, "my_generated_public": 0.5, "my_controlled_public": 0 } },
+            { "id": "second", "type": "arithmetic::Addi32", "inputs": { "y": 3, "my_generated_public": 0.0, "my_controlled_public": 0, "custom_reset": "hi" } }
+        ],
+        "edges": [
+            { "from": "first", "output": "out", "to": "second", "input": "x" }
+        ]
+    }"#;
+
+    fn registry() -> NodeRegistry {
+        let mut registry = NodeRegistry::new();
+        Addi32::__register(&mut registry);
+        registry
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = Program::from_json(ADDER).unwrap();
+        let mut loaded = program.build(&registry()).unwrap();
+        loaded.graph.run().unwrap();
+        assert_eq!(Some(7), loaded.graph.output::<i32>(loaded.nodes["second"], "out"));
+
+        // saving the graph gives back the same program
+        assert_eq!(program, loaded.to_program().unwrap());
+        assert_eq!(program, Program::from_json(&program.to_json()).unwrap());
+
+        let path = std::env::temp_dir().join(format!("delta-program-{}.json", std::process::id()));
+        save_program(&path, &program).unwrap();
+        let loaded = load_program(&path, &registry()).unwrap();
+        assert_eq!(program, loaded.to_program().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unnamed_nodes() {
+        let mut graph = crate::Graph::new();
+        let a = graph.add_node(Addi32::__initialize());
+        graph.set_input(a, "x", 1).unwrap();
+
+        let program = Program::from_graph(&graph, &Default::default()).unwrap();
+        assert_eq!("node0", program.nodes[0].id);
+        assert_eq!(Some(&serde_json::json!(1)), program.nodes[0].inputs.get("x"));
+
+        // a generated id never takes the name of another node
+        let b = graph.add_node(Addi32::__initialize());
+        graph.connect(a, "out", b, "x").unwrap();
+        let mut names: BTreeMap<String, NodeId> = BTreeMap::new();
+        names.insert("node1".to_owned(), a);
+        let program = Program::from_graph(&graph, &names).unwrap();
+        assert_eq!(vec!["node1", "node1_1"], program.nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>());
+        assert_eq!(("node1", "node1_1"), (program.edges[0].from.as_str(), program.edges[0].to.as_str()));
+    }
+
+    #[test]
+    fn errors() {
+        let program = |json: &str| Program::from_json(json).and_then(|p| p.build(&registry()).map(|_| ()));
+
+        assert!(matches!(program(r#"{ "format": "something-else", "version": 1, "nodes": [] }"#), Err(ProgramError::NotAProgram(_))));
+        assert!(matches!(program(r#"{ "format": "delta-program", "version": 3, "whatever": [] }"#), Err(ProgramError::UnsupportedVersion(3))));
+        assert!(matches!(program(r#"{ "format": "delta-program", "version": 1, "nodes": [{ "id": "a", "type": "Addi32" }] }"#),
+            Err(ProgramError::Registry(RegistryError::UnknownNode(_)))));
+        assert!(matches!(program(r#"{ "format": "delta-program", "version": 1, "nodes": [{ "id": "a", "type": "arithmetic::Addi32", "inputs": { "x": 1.5 } }] }"#),
+            Err(ProgramError::InvalidValue { ty: "i32", .. })));
+        assert!(matches!(program(r#"{ "format": "delta-program", "version": 1, "nodes": [{ "id": "a", "type": "arithmetic::Addi32" }, { "id": "a", "type": "arithmetic::Addi32" }] }"#),
+            Err(ProgramError::DuplicateNode(_))));
+        assert!(matches!(program(r#"{ "format": "delta-program", "version": 1, "nodes": [], "edges": [{ "from": "a", "output": "out", "to": "b", "input": "x" }] }"#),
+            Err(ProgramError::UnknownNode(_))));
+
+        // a loop would be saved as a node type that can not be loaded
+        let mut body = crate::Graph::new();
+        let add = body.add_node(Addi32::__initialize());
+        let mut graph = crate::Graph::new();
+        graph.add_node(Box::new(crate::Loop::repeat(body, 2, add, "out").unwrap()));
+        assert!(matches!(Program::from_graph(&graph, &Default::default()), Err(ProgramError::UnsupportedNode { ty, .. }) if ty == "delta_core::Loop"));
+    }
+}
@@ -3,7 +3,6 @@ use std::str::FromStr;
 use quote::{ ToTokens, spanned::Spanned };
 use syn::parse::Parser;
 
-#[macro_use]
 macro_rules! __delta_hashmap_literal {
     // https://stackoverflow.com/questions/28392008/more-concise-hashmap-initialization
     ($( $key: expr => $val: expr ),*) => {{
@@ -13,7 +12,6 @@ macro_rules! __delta_hashmap_literal {
     }}
 }
 
-#[macro_use]
 macro_rules! __delta_hashset_literal {
     ($($elem: expr),*) => {
         {
@@ -25,37 +23,67 @@ macro_rules! __delta_hashset_literal {
 // should code generated by the delta macros allways be prepended by two underscores? So it doesn't collide with any user created names
 
 // this macro generates the set and reset methods for all of the registered fields.
-// also generates __execute method to implement the DeltaNode trait
-// TODO: may want to move the __execute generation into the impl macro because it is easier to get access to the return type in there...
-#[proc_macro_derive(RegisterDeltaNode, attributes(delta_ignore, delta_noreset, delta_default))]
+// the DeltaNode trait itself is implemented by `delta_node_impl`, because that is where the on_execute return type is known
+#[proc_macro_derive(RegisterDeltaNode, attributes(delta_ignore, delta_noreset, delta_default, delta_output, delta_thread_local))]
 pub fn register_delta_node(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse_macro_input!(input as syn::ItemStruct);
 
     let name = ast.ident;
 
-    // TODO:    - Figure out how to automatically deal with type. So that whatever the items on_execute returns is the type for the DeltaNode
-    //          - If nothing is returned by the on_execute method then a None: Option should be returned
-    //          - Probably need some way to keep track of the state between 
-    
-
     // add all of the set_* functions for each of the exposed fields in the struct
     // also add all of the reset functions for each of the exposed fields
     let mut set_functions = vec![];
     let mut reset_functions  = vec![];
     let mut reset_calls = vec![];
 
+    // keep track of the ports as we go, inputs are every field that gets a set function
+    let mut input_ports = vec![];
+    let mut output_ports = vec![];
+    let mut optional_fields: u64 = 0;
+
+    // and what each field is, for `__describe`
+    let mut input_schemas = vec![];
+    let mut output_schemas = vec![];
+    let mut state_schemas = vec![];
+
+    // and how to set each field by name, fields that exist but are not inputs are an error of their own
+    let mut named_set_arms = vec![];
+    let mut not_inputs = vec![];
+
     for field in ast.fields.iter() {
         match &field.ident {
             Some(name) => {
 
                 // these fields should not be exposed to anything but internal and generated functions
-                if *name == "__set_fields" || *name == "__num_fields" {
+                if is_generated_field(name) {
+                    continue;
+                }
+
+                // output fields are filled by the node itself, so they never get set or reset functions
+                if let Ok(attr) = has_attribute(field, "delta_output") {
+                    if *name == "out" {
+                        return quote::quote_spanned! {name.span() => compile_error!("`out` is reserved for the on_execute output port.");}.into();
+                    }
+                    match impulse_payload(&field.ty) {
+                        Some(payload) => {
+                            let ty_name = type_name(payload);
+                            output_schemas.insert(output_schemas.len(), field_schema(field, quote::quote! { #ty_name }, "Reset", false));
+                            output_ports.insert(output_ports.len(), (name.clone(), ty_name, payload.clone()));
+                            not_inputs.insert(not_inputs.len(), name.to_string());
+                        },
+                        None => return quote::quote_spanned! {attr.__span() => compile_error!("`delta_output` fields must be an `Impulse<T>`.");}.into(),
+                    }
                     continue;
                 }
 
                 // check if we should ignore generating set and reset functions for this field
                 match delta_ignore(field) {
-                    Ok(ignore) => if ignore { continue; } else { },
+                    Ok(ignore) => if ignore {
+                        let ty_name = type_name(&field.ty);
+                        state_schemas.insert(state_schemas.len(), field_schema(field, quote::quote! { #ty_name }, "Never", false));
+                        not_inputs.insert(not_inputs.len(), name.to_string());
+                        continue;
+                    },
                     Err(ts) => return ts.into(), // user added incorrect arguments to the macro
                 }
 
@@ -63,19 +91,55 @@ pub fn register_delta_node(input: proc_macro::TokenStream) -> proc_macro::TokenS
                 let sfunc_name: proc_macro2::Ident = quote::format_ident!("__set_{}", name);
                 let rfunc_name: proc_macro2::Ident = quote::format_ident!("__reset_{}", name);
 
+                // each input gets one bit in __set_fields, in port order
+                let index = input_ports.len();
+                if index >= 64 {
+                    return quote::quote_spanned! {name.span() => compile_error!("A delta node can have at most 64 inputs.");}.into();
+                }
+                let bit: u64 = 1 << index;
+
+                // inputs with a default always hold a usable value, so they are optional
+                let required = has_attribute(field, "delta_default").is_err();
+                if !required {
+                    optional_fields |= bit;
+                }
+
+                input_ports.insert(input_ports.len(), (name.clone(), ty.clone(), sfunc_name.clone(), required));
+
+                let ty_name = type_name(ty);
+                let reset = if has_attribute(field, "delta_noreset").is_ok() { "NoReset" } else { "Reset" };
+                input_schemas.insert(input_schemas.len(), field_schema(field, quote::quote! { #ty_name }, reset, required));
+
+                let field_name = name.to_string();
+                named_set_arms.insert(named_set_arms.len(), quote::quote! {
+                    #field_name => match value.cast::<#ty>() {
+                        Ok(x) => { self.#sfunc_name(x); Ok(()) },
+                        Err(found) => Err(::delta_lib::SetError::TypeMismatch { field: #field_name.to_owned(), expected: #ty_name, found }),
+                    },
+                });
+
                 // generate set functions
                 set_functions.insert(set_functions.len(), quote::quote! {
                     pub fn #sfunc_name(&mut self, #name: #ty) {
                         self.#name = #name;
-                        self.__set_fields += 1;
+                        self.__set_fields |= #bit;
                     }
                 });
                 
+                // reset back to the user specified default if there is one
+                let default_value = match delta_default(field) {
+                    Ok(value) => value,
+                    Err(ts) => return ts.into(),
+                };
+
+                // a required input is missing again after a reset, an optional one is back to its default
+                let reset_bit = if required { quote::quote! { self.__set_fields &= !#bit; } } else { proc_macro2::TokenStream::new() };
+
                 // generate reset functions
                 reset_functions.insert(reset_functions.len(), quote::quote! {
                     pub fn #rfunc_name(&mut self) {
-                        self.#name = Default::default(); // TODO: allow user to change default value here...
-                        self.__set_fields -= 1;
+                        self.#name = #default_value;
+                        #reset_bit
                     } 
                 });
 
@@ -104,25 +168,62 @@ pub fn register_delta_node(input: proc_macro::TokenStream) -> proc_macro::TokenS
             pub fn __reset(&mut self) {
                 #(#reset_calls)*
             }
+
+            // true once every required input has been set since it was last reset
+            pub fn __is_ready(&self) -> bool {
+                let all_fields: u64 = if self.__num_fields == 64 { !0 } else { (1 << self.__num_fields) - 1 };
+                self.__set_fields & all_fields == all_fields
+            }
+
+            // the step the engine is currently on, only advances when the graph runs in clocked mode
+            pub fn __current_tick(&self) -> u64 {
+                self.__tick
+            }
+
+            // log a message without affecting what the node sends, the engine passes it on to its log sink
+            pub fn __log<S: Into<String>>(&mut self, level: ::delta_lib::LogLevel, text: S) {
+                self.__log_message(::delta_lib::DeltaMessage::new(level, text));
+            }
+
+            // log a message with extra fields, see `DeltaMessage::with_field`
+            pub fn __log_message(&mut self, mut message: ::delta_lib::DeltaMessage) {
+                message.tick = self.__tick;
+                self.__logs.push(message);
+            }
+
+            // whether the input with the given port index currently holds a value
+            pub fn __is_set(&self, index: usize) -> bool {
+                index < self.__num_fields && self.__set_fields & (1 << index) != 0
+            }
+
+            // set an input by its name, the value is converted to the type of the field (see `Value::cast`)
+            pub fn __set_by_name(&mut self, field: &str, value: ::delta_lib::Value) -> Result<(), ::delta_lib::SetError> {
+                match field {
+                    #(#named_set_arms)*
+                    #(#not_inputs => Err(::delta_lib::SetError::NotAnInput(#not_inputs.to_owned())),)*
+                    _ => Err(::delta_lib::SetError::UnknownField(field.to_owned())),
+                }
+            }
         }
     };
 
     // generate code to call both init steps
-    let field_list: Vec<proc_macro2::TokenStream> = ast.fields.iter().filter(|x| {
+    // every field (ignored or not) starts out with either its `delta_default` value or the Default of its type
+    let mut field_list: Vec<proc_macro2::TokenStream> = vec![];
+    for field in ast.fields.iter() {
         // filter out all of the fields that we generated
-        if let Some(name) = &x.ident {
-            return *name != "__num_fields" && *name != "__set_fields"
-        }
-        false
-    }).map(|x| {
-        // then collect the names of each of the fields
-        match &x.ident {
-            Some(name) => quote::quote!{ #name },
-            _ => proc_macro2::TokenStream::new()
+        let name = match &field.ident {
+            Some(name) if !is_generated_field(name) => name,
+            _ => continue,
+        };
+
+        match delta_default(field) {
+            Ok(value) => field_list.insert(field_list.len(), quote::quote!{ #name: #value }),
+            Err(ts) => return ts.into(),
         }
-    }).collect();
+    }
 
-    let default_init = default_initialize(&field_list, &name);
+    let default_init = default_initialize(&field_list, &name, input_ports.len(), optional_fields);
     
     let output_init = quote::quote! {
         impl #name {
@@ -130,34 +231,57 @@ pub fn register_delta_node(input: proc_macro::TokenStream) -> proc_macro::TokenS
         }
     };
 
-    // add an implementation for the required execution code
-    let output_deltanode = quote::quote! { 
-        impl DeltaNode<Impulse<i32>, #name> for #name {
-            fn __execute(mut self) -> Impulse<i32> {
-                self.__pre_execute();
-                let res: Impulse<i32> = self.__on_execute();
-                self.__post_execute();
-                res
+    // nodes are executed on worker threads unless they opt out, e.g. because they hold an `Rc`
+    let thread_local = ast.attrs.iter().any(|attr| attr.path.is_ident("delta_thread_local"));
+
+    let output_ports = generate_ports(&name, &input_ports, &output_ports, thread_local);
+
+    // lets the node be created by name, the name is the full path of the type e.g. `arithmetic::Addi32`
+    let output_register = quote::quote! {
+        impl #name {
+            pub const __TYPE_NAME: &'static str = concat!(module_path!(), "::", stringify!(#name));
+
+            pub fn __register(registry: &mut ::delta_lib::NodeRegistry) {
+                registry.register(<#name>::__TYPE_NAME, <#name as ::delta_lib::DeltaPorts>::__ports(), <#name>::__describe(), Box::new(|_| -> Result<Box<dyn ::delta_lib::DynDeltaNode>, ::delta_lib::RegistryError> {
+                    Ok(<#name as ::delta_lib::DeltaNode<_, #name>>::__initialize())
+                }));
             }
+        }
+    };
 
-            fn __initialize() -> Box<#name> {
-                let mut ret: Box<#name> = <#name>::__default_initialize();
-                ret.__custom_initialize();
-                ret
+    // the on_execute return value is the first output, its doc comments are on the method so there are none here
+    let struct_doc = doc_comment(&ast.attrs);
+    let output_describe = quote::quote! {
+        impl #name {
+            pub fn __describe() -> ::delta_lib::NodeSchema {
+                ::delta_lib::NodeSchema {
+                    name: ::std::borrow::Cow::Borrowed(<#name>::__TYPE_NAME),
+                    doc: ::std::borrow::Cow::Borrowed(#struct_doc),
+                    inputs: vec![ #(#input_schemas),* ],
+                    outputs: vec![
+                        ::delta_lib::FieldSchema { name: ::std::borrow::Cow::Borrowed("out"), ty: <#name>::__RETURN_TYPE, default: None, reset: ::delta_lib::ResetPolicy::Reset, public: false, required: false, doc: "" },
+                        #(#output_schemas),*
+                    ],
+                    state: vec![ #(#state_schemas),* ],
+                }
             }
         }
     };
-    
+
     let output = quote::quote! {
-        #output_deltanode
         #output_init
         #output_set_reset
+        #output_ports
+        #output_register
+        #output_describe
     };
 
     output.into()
 }
 
-// this macro generates the extra two fields __num_fields and __set_fields that are required under the hood
+// this macro generates the extra fields __num_fields, __set_fields, __tick and __logs that are required under the hood
+// __num_fields is the number of inputs, __set_fields has a bit for each input that is set, __tick is the engine's current step
+// and __logs holds messages logged by the node until the engine collects them
 #[proc_macro_attribute]
 pub fn delta_node_struct(_args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut item_struct = syn::parse_macro_input!(input as syn::ItemStruct);
@@ -166,12 +290,22 @@ pub fn delta_node_struct(_args: proc_macro::TokenStream, input: proc_macro::Toke
     if let syn::Fields::Named(ref mut fields) = item_struct.fields {
         fields.named.push(
             syn::Field::parse_named
-                .parse2(quote::quote! { __num_fields: i32 })
+                .parse2(quote::quote! { __num_fields: usize })
                 .unwrap(),
         );
         fields.named.push(
             syn::Field::parse_named
-                .parse2(quote::quote! { __set_fields: i32 })
+                .parse2(quote::quote! { __set_fields: u64 })
+                .unwrap(),
+        );
+        fields.named.push(
+            syn::Field::parse_named
+                .parse2(quote::quote! { __tick: u64 })
+                .unwrap(),
+        );
+        fields.named.push(
+            syn::Field::parse_named
+                .parse2(quote::quote! { __logs: Vec<::delta_lib::DeltaMessage> })
                 .unwrap(),
         );
     }
@@ -180,7 +314,7 @@ pub fn delta_node_struct(_args: proc_macro::TokenStream, input: proc_macro::Toke
         #item_struct
     };
 
-    return output.into();
+    output.into()
 }
 
 // this macro goes through the node implementation
@@ -193,25 +327,48 @@ pub fn delta_node_impl(args: proc_macro::TokenStream, input: proc_macro::TokenSt
 
     // user specifies which of their functions map to the Delta API
     // also checks for invalid args, DOES NOT check if the args are actually correct (if the names are right)
-    let function_mappings: ::std::collections::HashMap<String, String>;
-    match generate_mappings(args) {
-        Ok(x) => function_mappings = x,
+    let function_mappings = match generate_mappings(&args) {
+        Ok(x) => x,
         Err(err) => return err.into(),
-    }
+    };
+
+    // user can also choose where their init and post_exec run relative to the built-in lifecycle
+    let (init_order, post_exec_order) = match generate_orders(&args) {
+        Ok(x) => x,
+        Err(err) => return err.into(),
+    };
 
     // TODO:  * maybe add flags? Like to not generate default methods to prevent unexpected behavior, 
     //          b/c then it won't compile if everything isn't specified
 
-    // need to get the name of the implementation (the `for ...` I guess). Needed to properly generate 
+    // the type the impl is for, needed to generate the DeltaNode implementation
+    let name = item_impl.self_ty.clone();
     
-    // gather up all of the methods
+    // gather up all of the methods, also take the `#[delta(...)]` attributes off of them.
+    // those only tell this macro which API a method implements, they don't mean anything to the compiler
     let mut methods = vec![];
-    for item in item_impl.items.iter() {
-        match item {
-            syn::ImplItem::Method(method) => {
-                methods.insert(methods.len(), method.clone());
-            },
-            _ => continue,
+    let mut attr_mappings = vec![];
+    for item in item_impl.items.iter_mut() {
+        if let syn::ImplItem::Method(method) = item {
+            let mut delta_attrs = vec![];
+            method.attrs.retain(|attr| {
+                if attr.path.is_ident("delta") {
+                    delta_attrs.insert(delta_attrs.len(), attr.clone());
+                    return false;
+                }
+                true
+            });
+
+            for attr in delta_attrs.iter() {
+                match parse_delta_attribute(attr) {
+                    Ok(apis) => for api in apis {
+                        attr_mappings.insert(attr_mappings.len(), (api.to_string(), method.sig.ident.to_string(), api.span()));
+                    },
+                    Err(err) => return err.into(),
+                }
+            }
+
+            methods.insert(methods.len(), method.clone());
         }
     }
 
@@ -231,40 +388,106 @@ pub fn delta_node_impl(args: proc_macro::TokenStream, input: proc_macro::TokenSt
                                                                                     "on_exec".to_owned() => "__on_execute",
                                                                                     "post_exec".to_owned() => "__post_execute"];
 
-    // go through each of the methods and check if the DeltaNode method requirements are satisfied
-    // also generate any needed wrapper function if found attribute
-    for method in methods.iter() {
+    // figure out which of the user's methods implements which API.
+    // a method can be mapped in the macro arguments or with a `#[delta(...)]` attribute, but each API only once
+    let mut hooks = ::std::collections::HashMap::<String, (String, proc_macro2::Span)>::new();
+    for (api_name, cus_name) in function_mappings.iter() {
+        hooks.insert(api_name.clone(), (cus_name.value(), cus_name.span()));
+    }
+    for (api_name, method_name, span) in attr_mappings.iter() {
+        if hooks.contains_key(api_name) {
+            let msg = format!("`{}` is already mapped to a method.", api_name);
+            return quote::quote_spanned! {*span => compile_error!(#msg);}.into();
+        }
+        hooks.insert(api_name.clone(), (method_name.clone(), *span));
+    }
 
-        let method_name = method.sig.ident.to_string();
-        let method_return = get_return(method);
+    // the user can also just write the generated name themselves, then there is nothing to wrap
+    for (api_name, wrapper) in attr_name_to_wrap_name.iter() {
+        if let Some(method) = methods.iter().find(|m| m.sig.ident == wrapper) {
+            if hooks.contains_key(api_name) {
+                let msg = format!("`{}` is defined directly, so `{}` can not also be mapped to another method.", wrapper, api_name);
+                return quote::quote_spanned! {method.sig.ident.span() => compile_error!(#msg);}.into();
+            }
+            if let Err(err) = check_signature(method, api_name, false, init_order.as_str()) {
+                return err.into();
+            }
+            if let Some(m_flag) = method_flags.get_mut(*wrapper) {
+                *m_flag = true;
+            }
+        }
+    }
 
-        // update flag if we found a method with a correct name
-        // if name is found then continue to next method because we don't care about any wrapper attributes
-        if let Some(m_flag) = method_flags.get_mut(&method_name) {
-            *m_flag = true;
-            continue;
+    // every mapped method has to exist and have the right signature
+    let mut mapped_methods = ::std::collections::HashMap::<String, syn::ImplItemMethod>::new();
+    for (api_name, (method_name, span)) in hooks.iter() {
+        let method = match methods.iter().find(|m| m.sig.ident == method_name) {
+            Some(method) => method,
+            None => {
+                let msg = format!("No method named `{}` in this impl to use as `{}`.", method_name, api_name);
+                return quote::quote_spanned! {*span => compile_error!(#msg);}.into();
+            },
+        };
+        if let Err(err) = check_signature(method, api_name, true, init_order.as_str()) {
+            return err.into();
         }
-        
-        // go through all of the attributes for each method and check if any specify wrappers to generate
-        for attr in method.attrs.iter() {
-            let attr_name = attr.tokens.to_string();
-            let wrap_name = attr_name_to_wrap_name.get(&attr_name);
-        
-            match wrap_name {
-                Some(wrapper) => {
-                    // create a wrapper function
-                    generated_functions.insert(generated_functions.len(), 
-                        generate_wrapper_s(wrapper, &method_name, None, true, true, true)
-                    );
-                    
-                    // update the flag 
-                    if let Some(m_flag) = method_flags.get_mut(*wrapper) {
-                        *m_flag = true;
-                        // purposfully choosing not to stop iterating over all of the other attributes because a user may want to reuse a method
-                        // break; 
+        mapped_methods.insert(api_name.clone(), method.clone());
+    }
+
+    // the on_execute method decides what the node returns, so find it first.
+    // either the user mapped one of their methods to `on_exec`, or they wrote `__on_execute` themselves.
+    // if there is neither then the default on_execute is generated, which returns a NOOP of `Impulse<()>`
+    let output_type: syn::Type = match mapped_methods.get("on_exec").or_else(|| methods.iter().find(|m| m.sig.ident == "__on_execute")) {
+        Some(method) => get_return(method),
+        None => syn::parse_quote! { Impulse<()> },
+    };
+
+    // a mapped on_exec can also return `Result<Impulse<T>, E>`, in which case the node returns `Impulse<T>`
+    // and any error is sent as an `Impulse::ERROR`
+    let (output_type, fallible) = match result_ok_type(&output_type) {
+        Some(ok) if mapped_methods.contains_key("on_exec") => (ok.clone(), true),
+        _ => (output_type, false),
+    };
+    let output_type_s = output_type.to_token_stream().to_string();
+
+    // any of the execution hooks can be an `async fn`, then the wrapper (and the execution) is async too
+    let mut async_hooks = ::std::collections::HashSet::<&str>::new();
+    for (api_name, wrapper) in attr_name_to_wrap_name.iter() {
+        let method = mapped_methods.get(api_name).or_else(|| methods.iter().find(|m| m.sig.ident == wrapper));
+        if let Some(method) = method.filter(|m| m.sig.asyncness.is_some()) {
+            if api_name == "init" {
+                return quote::quote_spanned! {method.sig.asyncness.__span() => compile_error!("`init` can not be async, only `pre_exec`, `on_exec` and `post_exec` can.");}.into();
+            }
+            async_hooks.insert(*wrapper);
+        }
+    }
+
+    // generate wrappers for all of the mapped methods
+    for (api_name, method) in mapped_methods.iter() {
+        let cus_name = method.sig.ident.to_string();
+        if let Some(wrapper) = attr_name_to_wrap_name.get(api_name) {
+            // the on_execute wrapper has to pass through whatever the user's method returns
+            let wrap_return = if *wrapper == "__on_execute" { Some(output_type_s.as_str()) } else { None };
+            if *wrapper == "__on_execute" && fallible {
+                generated_functions.insert(generated_functions.len(), fallible_on_execute(&cus_name, &output_type, async_hooks.contains(wrapper)));
+            } else if async_hooks.contains(wrapper) {
+                generated_functions.insert(generated_functions.len(), async_wrapper(wrapper, &cus_name, wrap_return.map(|_| &output_type)));
+            } else if *wrapper == "__custom_initialize" && init_order == "before_default" {
+                // there is no node yet, so the init hook is static
+                let cus_name = quote::format_ident!("{}", cus_name);
+                generated_functions.insert(generated_functions.len(), quote::quote! {
+                    pub fn __custom_initialize() {
+                        Self::#cus_name();
                     }
-                },
-                _ => continue,
+                });
+            } else {
+                generated_functions.insert(generated_functions.len(),
+                    generate_wrapper_s(wrapper, &cus_name, wrap_return, true, true, true)
+                );
+            }
+
+            if let Some(m_flag) = method_flags.get_mut(*wrapper) {
+                *m_flag = true;
             }
         }
     }
@@ -273,15 +496,27 @@ pub fn delta_node_impl(args: proc_macro::TokenStream, input: proc_macro::TokenSt
     for (method, flag) in &method_flags {
         if !flag {
             generated_functions.insert(generated_functions.len(), match method.as_ref() {
-                "__custom_initialize" => default_custom_initialize(),
+                "__custom_initialize" => default_custom_initialize(init_order == "after_default"),
                 "__pre_execute" => default_pre_execute(),
-                "__on_execute" => default_on_execute(),
+                "__on_execute" => default_on_execute(&output_type_s),
                 "__post_execute" => default_post_execute(),
                 _ => proc_macro2::TokenStream::new(),
             });
         }
     }
 
+    // the port descriptors generated by RegisterDeltaNode need to know what the `out` port carries
+    let payload = impulse_payload(&output_type).unwrap_or(&output_type);
+    let (return_type, return_dynamic) = (type_name(payload), is_value(payload));
+    generated_functions.insert(generated_functions.len(), quote::quote! {
+        pub const __RETURN_TYPE: &'static str = #return_type;
+    });
+    generated_functions.insert(generated_functions.len(), quote::quote! {
+        pub fn __return_dynamic() -> bool {
+            #return_dynamic
+        }
+    });
+
     // can either push back into array, or if name can be figured out easily then that seems like a neater way of doing it, without modifying any written code
     // can't seem to easily get the name, but this is straightforward, but it does 'modify' the input code, which I don't like
     for md in generated_functions.iter() {
@@ -289,8 +524,88 @@ pub fn delta_node_impl(args: proc_macro::TokenStream, input: proc_macro::TokenSt
         item_impl.items.push(q);
     }
 
+    // init either gets the node after the default values are in, or runs (statically) before there is a node
+    let initialize = match init_order.as_str() {
+        "before_default" => quote::quote! {
+            <#name>::__custom_initialize();
+            <#name>::__default_initialize()
+        },
+        _ => quote::quote! {
+            let mut ret: Box<#name> = <#name>::__default_initialize();
+            ret.__custom_initialize();
+            ret
+        },
+    };
+
+    // the registered fields are reset after every execution unless the user replaces that with their post_exec.
+    // calls to the async hooks have to be awaited
+    let hook_call = |wrapper: &str| {
+        let wrapper = quote::format_ident!("{}", wrapper);
+        if async_hooks.contains(&*wrapper.to_string()) {
+            quote::quote! { self.#wrapper().await }
+        } else {
+            quote::quote! { self.#wrapper() }
+        }
+    };
+    let (pre_execute, on_execute, post_hook) = (hook_call("__pre_execute"), hook_call("__on_execute"), hook_call("__post_execute"));
+    let post_execute = match post_exec_order.as_str() {
+        "after_reset" => quote::quote! { self.__reset(); #post_hook; },
+        "replace" => quote::quote! { #post_hook; },
+        _ => quote::quote! { #post_hook; self.__reset(); },
+    };
+    let execute_body = quote::quote! {
+        #pre_execute;
+        let res: #output_type = #on_execute;
+        #post_execute
+        res
+    };
+
+    // an async node is executed in `__execute_async`, running it directly blocks until it is done.
+    // either way `__execute_future` gives the engine something it can run next to other nodes
+    let (execute, execute_future) = if async_hooks.is_empty() {
+        (execute_body, quote::quote! {
+            pub fn __execute_future(&mut self) -> ::delta_lib::DeltaFuture<'_, #output_type> {
+                Box::pin(async move { <#name as DeltaNode<#output_type, #name>>::__execute(self) })
+            }
+        })
+    } else {
+        (quote::quote! { ::delta_lib::block_on(self.__execute_async()) }, quote::quote! {
+            pub async fn __execute_async(&mut self) -> #output_type {
+                #execute_body
+            }
+
+            pub fn __execute_future(&mut self) -> ::delta_lib::DeltaFuture<'_, #output_type> {
+                Box::pin(self.__execute_async())
+            }
+        })
+    };
+
+    // add an implementation for the required execution code, returning whatever on_execute returns
+    let output_deltanode = quote::quote! { 
+        impl #name {
+            #execute_future
+
+            // the on_execute return value as a `Value`, how it is converted is picked from its type (see `delta_lib::ToValue`)
+            fn __return_value(ret: #output_type) -> ::delta_lib::Impulse<::delta_lib::Value> {
+                use ::delta_lib::{ ViaFrom, ViaOpaque };
+                ret.map(|x| (&&::delta_lib::ToValue::new(x)).to_value())
+            }
+        }
+
+        impl DeltaNode<#output_type, #name> for #name {
+            fn __execute(&mut self) -> #output_type {
+                #execute
+            }
+
+            fn __initialize() -> Box<#name> {
+                #initialize
+            }
+        }
+    };
+
     let tokens = quote::quote! {
         #item_impl
+        #output_deltanode
     };
 
     // preferred way of getting the output, maybe someday :)
@@ -305,31 +620,163 @@ pub fn delta_node_impl(args: proc_macro::TokenStream, input: proc_macro::TokenSt
     tokens.into()
 }
 
-fn default_initialize(field_list: &[proc_macro2::TokenStream], name: &syn::Ident, ) -> proc_macro2::TokenStream {
-    // TODO: if using the default initialize the value should be the user specified default value if it exists
-    if let Ok(num_fields) = proc_macro2::TokenStream::from_str(&field_list.len().to_string()) {
-        quote::quote! {
-            pub fn __default_initialize() -> Box<#name> {
-                Box::new( #name { #(#field_list: Default::default(), )* __num_fields: #num_fields, __set_fields: 0})
-            }
+fn default_initialize(field_list: &[proc_macro2::TokenStream], name: &syn::Ident, num_fields: usize, optional_fields: u64) -> proc_macro2::TokenStream {
+    // each entry of the field list is already `field: default_value`
+    // optional inputs start out set since they already hold their default
+    quote::quote! {
+        pub fn __default_initialize() -> Box<#name> {
+            Box::new( #name { #(#field_list, )* __num_fields: #num_fields, __set_fields: #optional_fields, __tick: 0, __logs: Vec::new()})
         }
-    } else {
-        proc_macro2::TokenStream::new()
     }
 
 }
 
-fn default_custom_initialize() -> proc_macro2::TokenStream {
+// generates the DeltaPorts implementation, which exposes the inputs and outputs by index so an engine can wire them up
+fn generate_ports(name: &syn::Ident, inputs: &[(syn::Ident, syn::Type, syn::Ident, bool)], outputs: &[(syn::Ident, String, syn::Type)], thread_local: bool) -> proc_macro2::TokenStream {
+    let mut descriptors = vec![];
+    let mut set_arms = vec![];
+    let mut unset_arms = vec![];
+    let mut collect_outputs = vec![];
+
+    for (index, (field, ty, setter, required)) in inputs.iter().enumerate() {
+        let field_name = field.to_string();
+        let ty_name = type_name(ty);
+        let dynamic = is_value(ty);
+        let error = is_delta_error(ty);
+        descriptors.insert(descriptors.len(), quote::quote! {
+            ::delta_lib::PortDescriptor { name: ::std::borrow::Cow::Borrowed(#field_name), ty: #ty_name, index: #index, kind: ::delta_lib::PortKind::Input, required: #required, dynamic: #dynamic, error: #error }
+        });
+        set_arms.insert(set_arms.len(), quote::quote! {
+            #index => match ::std::clone::Clone::clone(value).cast::<#ty>() {
+                Ok(x) => { self.#setter(x); Ok(()) },
+                Err(found) => Err(::delta_lib::PortError::TypeMismatch { port: #field_name.to_owned(), expected: #ty_name, found: found.kind() }),
+            },
+        });
+        let reset = quote::format_ident!("__reset_{}", field);
+        unset_arms.insert(unset_arms.len(), quote::quote! {
+            #index => self.#reset(),
+        });
+    }
+
+    // the on_execute return value is always the first output
+    descriptors.insert(descriptors.len(), quote::quote! {
+        ::delta_lib::PortDescriptor { name: ::std::borrow::Cow::Borrowed("out"), ty: <#name>::__RETURN_TYPE, index: 0, kind: ::delta_lib::PortKind::Output, required: false, dynamic: <#name>::__return_dynamic(), error: false }
+    });
+
+    for (index, (field, ty_name, payload)) in outputs.iter().enumerate() {
+        let field_name = field.to_string();
+        let index = index + 1;
+        let dynamic = is_value(payload);
+        descriptors.insert(descriptors.len(), quote::quote! {
+            ::delta_lib::PortDescriptor { name: ::std::borrow::Cow::Borrowed(#field_name), ty: #ty_name, index: #index, kind: ::delta_lib::PortKind::Output, required: false, dynamic: #dynamic, error: false }
+        });
+        collect_outputs.insert(collect_outputs.len(), quote::quote! {
+            outputs.push(::std::mem::take(&mut self.#field).map(|x| (&&::delta_lib::ToValue::new(x)).to_value()));
+        });
+    }
+
+    // if this fails to compile the node is not `Send`, then it needs `#[delta_thread_local]`
+    let (send_view, send_node) = if thread_local {
+        (quote::quote! { None }, proc_macro2::TokenStream::new())
+    } else {
+        (quote::quote! { Some(self) }, quote::quote! {
+            impl ::delta_lib::SendNode for #name {
+                fn __execute_send(&mut self) -> Vec<::delta_lib::Impulse<::delta_lib::Value>> {
+                    let ret = ::delta_lib::DeltaNode::__execute(self);
+                    <#name>::__collect_outputs(self, <#name>::__return_value(ret))
+                }
+            }
+        })
+    };
+
+    quote::quote! {
+        impl #name {
+            // what the node sent on every output port after it executed, in port order. Output fields are reset to NOOP.
+            // the type of each port is known here, so `ToValue` picks the conversion without checking the type at run time
+            fn __collect_outputs(&mut self, ret: ::delta_lib::Impulse<::delta_lib::Value>) -> Vec<::delta_lib::Impulse<::delta_lib::Value>> {
+                use ::delta_lib::{ ViaFrom, ViaOpaque };
+                let mut outputs = vec![ret];
+                #(#collect_outputs)*
+                outputs
+            }
+        }
+
+        impl ::delta_lib::DeltaPorts for #name {
+            fn __ports() -> Vec<::delta_lib::PortDescriptor> {
+                vec![ #(#descriptors),* ]
+            }
+
+            fn __node_type() -> &'static str {
+                <#name>::__TYPE_NAME
+            }
+
+            fn __schema() -> ::delta_lib::NodeSchema {
+                <#name>::__describe()
+            }
+
+            fn __set_port(&mut self, index: usize, value: &::delta_lib::Value) -> Result<(), ::delta_lib::PortError> {
+                match index {
+                    #(#set_arms)*
+                    _ => Err(::delta_lib::PortError::UnknownPort(index)),
+                }
+            }
+
+            fn __unset_port(&mut self, index: usize) {
+                match index {
+                    #(#unset_arms)*
+                    _ => {},
+                }
+            }
+
+            fn __set_named(&mut self, field: &str, value: ::delta_lib::Value) -> Result<(), ::delta_lib::SetError> {
+                <#name>::__set_by_name(self, field, value)
+            }
+
+            fn __is_ready(&self) -> bool {
+                <#name>::__is_ready(self)
+            }
+
+            fn __set_tick(&mut self, tick: u64) {
+                self.__tick = tick;
+            }
+
+            fn __take_logs(&mut self) -> Vec<::delta_lib::DeltaMessage> {
+                ::std::mem::take(&mut self.__logs)
+            }
+
+            fn __execute_ports(&mut self) -> Vec<::delta_lib::Impulse<::delta_lib::Value>> {
+                let ret = ::delta_lib::DeltaNode::__execute(self);
+                <#name>::__collect_outputs(self, <#name>::__return_value(ret))
+            }
+
+            fn __execute_ports_async(&mut self) -> ::delta_lib::DeltaFuture<'_, Vec<::delta_lib::Impulse<::delta_lib::Value>>> {
+                Box::pin(async move {
+                    let ret = <#name>::__execute_future(self).await;
+                    <#name>::__collect_outputs(self, <#name>::__return_value(ret))
+                })
+            }
+
+            fn __send_view(&mut self) -> Option<&mut dyn ::delta_lib::SendNode> {
+                #send_view
+            }
+        }
+
+        #send_node
+    }
+}
+
+fn default_custom_initialize(use_self: bool) -> proc_macro2::TokenStream {
     // the default custom initialize should also just be an empty placeholder
-    generate_wrapper_s("__custom_initialize", "", None, true, true, false)
+    // it is static if it is run before the default initialization
+    generate_wrapper_s("__custom_initialize", "", None, true, use_self, false)
 }
 
 fn default_pre_execute() -> proc_macro2::TokenStream {
-    // default pre-execute is an empty, static, placeholder function so that everything compiles
-    generate_wrapper_s("__pre_execute", "", None, true, false, false)
+    // default pre-execute is an empty placeholder function so that everything compiles
+    generate_wrapper_s("__pre_execute", "", None, true, true, false)
 }
 
-fn default_on_execute() -> proc_macro2::TokenStream {
+fn default_on_execute(output_type: &str) -> proc_macro2::TokenStream {
     // It is starting to feel more and more like I should get ride of the DeltaNode<T> nonsense, 
     // but at the same time is GUARANTEES I return something, even if that something is None, it seems like it would be better than returning ()
     // let tokens = quote! {
@@ -341,12 +788,143 @@ fn default_on_execute() -> proc_macro2::TokenStream {
     // tokens
 
     // basically the above, but is static and generated using a function. All of the above comments still apply though
-    generate_wrapper_s("__on_execute", "Impulse::<i32>::NOOP", Some("Impulse<i32>"), true, true, false)
+    // the return type is whatever the node's DeltaNode implementation returns, `Impulse<()>` if nothing else is known
+    generate_wrapper_s("__on_execute", "Impulse::NOOP", Some(output_type), true, true, false)
+}
+
+// gets `init_order` and `post_exec_order` out of the macro arguments, these place the custom init and post_exec in the lifecycle
+// init_order:      - "after_default" (default) runs init on the node once its default values are in
+//                  - "before_default" runs init before the node is created, so init is static (no `self`)
+// post_exec_order: - "before_reset" (default) runs post_exec and then resets the registered fields
+//                  - "after_reset" resets the registered fields and then runs post_exec
+//                  - "replace" only runs post_exec, the fields are not reset unless post_exec does it
+fn generate_orders(args: &[syn::NestedMeta]) -> Result<(String, String), proc_macro2::TokenStream> {
+    let mut init_order = None;
+    let mut post_exec_order = None;
+
+    for nested_meta in args.iter() {
+        if let syn::NestedMeta::Meta(syn::Meta::NameValue(map_pair)) = nested_meta {
+            let (order, valid, msg) = if map_pair.path.is_ident("init_order") {
+                (&mut init_order, vec!["after_default", "before_default"], "`init_order` must be one of: \"after_default\", \"before_default\".")
+            } else if map_pair.path.is_ident("post_exec_order") {
+                (&mut post_exec_order, vec!["before_reset", "after_reset", "replace"], "`post_exec_order` must be one of: \"before_reset\", \"after_reset\", \"replace\".")
+            } else {
+                continue;
+            };
+
+            if order.is_some() {
+                return Err(quote::quote_spanned! {map_pair.__span() => compile_error!("Can not have duplicate orders.")});
+            }
+
+            match &map_pair.lit {
+                syn::Lit::Str(value) if valid.contains(&&*value.value()) => *order = Some(value.value()),
+                lit => return Err(quote::quote_spanned! {lit.span() => compile_error!(#msg)}),
+            }
+        }
+    }
+
+    Ok((init_order.unwrap_or_else(|| "after_default".to_owned()), post_exec_order.unwrap_or_else(|| "before_reset".to_owned())))
+}
+
+// parses `#[delta(on_exec)]`, several APIs can be given at once like `#[delta(pre_exec, post_exec)]`
+fn parse_delta_attribute(attr: &syn::Attribute) -> Result<Vec<syn::Ident>, proc_macro2::TokenStream> {
+    let valid_apis = __delta_hashset_literal!["init", "pre_exec", "on_exec", "post_exec"];
+    let parser = syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated;
+    let apis = match attr.parse_args_with(parser) {
+        Ok(apis) => apis,
+        Err(_) => return Err(quote::quote_spanned! {attr.__span() => compile_error!("Expected `#[delta(...)]` with one or more of: `init`, `pre_exec`, `on_exec`, `post_exec`.");}),
+    };
+
+    let mut idents = vec![];
+    for api in apis {
+        if !valid_apis.contains(&*api.to_string()) {
+            return Err(quote::quote_spanned! {api.span() => compile_error!("Invalid Delta API, must be one of: `init`, `pre_exec`, `on_exec`, `post_exec`.");});
+        }
+        idents.insert(idents.len(), api);
+    }
+    Ok(idents)
+}
+
+// checks a method can be used for an API.
+// every API takes `&mut self` and nothing else (except init run before_default, which takes nothing),
+// only on_exec returns something: an `Impulse<T>`, or if the method is mapped (and so gets wrapped) a `Result<Impulse<T>, E>`
+fn check_signature(method: &syn::ImplItemMethod, api_name: &str, mapped: bool, init_order: &str) -> Result<(), proc_macro2::TokenStream> {
+    let sig = &method.sig;
+    let name = sig.ident.to_string();
+
+    if api_name == "init" && init_order == "before_default" {
+        if !sig.inputs.is_empty() || !sig.generics.params.is_empty() {
+            let msg = format!("`{}` is used as `init` before the default initialization, so it can not take any arguments (not even `self`).", name);
+            return Err(quote::quote_spanned! {sig.__span() => compile_error!(#msg);});
+        }
+    } else {
+        let takes_mut_self = sig.inputs.len() == 1 && matches!(sig.inputs.first(), Some(syn::FnArg::Receiver(r)) if r.reference.is_some() && r.mutability.is_some());
+        if !takes_mut_self || !sig.generics.params.is_empty() {
+            let msg = format!("`{}` is used as `{}`, so it must take `&mut self` and nothing else.", name, api_name);
+            return Err(quote::quote_spanned! {sig.__span() => compile_error!(#msg);});
+        }
+    }
+
+    if api_name == "on_exec" {
+        let ret = get_return(method);
+        let impulse = match result_ok_type(&ret) {
+            Some(ok) if mapped => impulse_payload(ok).is_some(),
+            _ => impulse_payload(&ret).is_some(),
+        };
+        if !impulse {
+            let msg = if mapped {
+                format!("`{}` is used as `on_exec`, so it must return an `Impulse<T>` or a `Result<Impulse<T>, E>`.", name)
+            } else {
+                format!("`{}` must return an `Impulse<T>`.", name)
+            };
+            return Err(quote::quote_spanned! {sig.output.__span() => compile_error!(#msg);});
+        }
+    } else if let syn::ReturnType::Type(_, ty) = &sig.output {
+        if !matches!(&**ty, syn::Type::Tuple(t) if t.elems.is_empty()) {
+            let msg = format!("`{}` is used as `{}`, so it can not return anything.", name, api_name);
+            return Err(quote::quote_spanned! {sig.output.__span() => compile_error!(#msg);});
+        }
+    }
+
+    Ok(())
+}
+
+// wraps an on_exec that returns a Result, errors are turned into `Impulse::ERROR`
+fn fallible_on_execute(func_name: &str, output_type: &syn::Type, asyncness: bool) -> proc_macro2::TokenStream {
+    let func_name = quote::format_ident!("{}", func_name);
+    let (ts_async, ts_await) = if asyncness { (quote::quote! { async }, quote::quote! { .await }) } else { (quote::quote! {}, quote::quote! {}) };
+    quote::quote! {
+        pub #ts_async fn __on_execute(&mut self) -> #output_type {
+            match self.#func_name() #ts_await {
+                Ok(impulse) => impulse,
+                Err(e) => ::delta_lib::Impulse::ERROR(::std::convert::Into::<::delta_lib::DeltaError>::into(e)),
+            }
+        }
+    }
+}
+
+// wraps an async hook, the wrapper is async as well
+fn async_wrapper(wrap_name: &str, func_name: &str, func_return: Option<&syn::Type>) -> proc_macro2::TokenStream {
+    let wrap_name = quote::format_ident!("{}", wrap_name);
+    let func_name = quote::format_ident!("{}", func_name);
+    match func_return {
+        Some(ret) => quote::quote! {
+            pub async fn #wrap_name(&mut self) -> #ret {
+                self.#func_name().await
+            }
+        },
+        None => quote::quote! {
+            pub async fn #wrap_name(&mut self) {
+                self.#func_name().await;
+            }
+        },
+    }
 }
 
 fn default_post_execute() -> proc_macro2::TokenStream {
-    // default post execute resets all of registered fields 
-    generate_wrapper_s("__post_execute", "__reset", None, true, true, true)
+    // default post execute is an empty placeholder too, the reset of the registered fields is done by `__execute`
+    // so that it can happen before or after a custom post execute (see `post_exec_order`)
+    generate_wrapper_s("__post_execute", "", None, true, true, false)
 }
 
 fn _generate_wrapper(wrap_name: proc_macro2::TokenStream, func_name: proc_macro2::TokenStream, func_return: Option<proc_macro2::TokenStream>, public: bool, use_self: bool, call_func: bool) -> proc_macro2::TokenStream {
@@ -388,7 +966,7 @@ fn _generate_wrapper(wrap_name: proc_macro2::TokenStream, func_name: proc_macro2
     };
 
     // build the wrapping function
-    let tokens = match func_return {
+    match func_return {
         Some(ret) => quote::quote! {
             #ts_pub fn #wrap_name(#ts_self_arg) #ret {
                 #ts_self_call #func_name #ts_call
@@ -399,9 +977,7 @@ fn _generate_wrapper(wrap_name: proc_macro2::TokenStream, func_name: proc_macro2
                 #ts_self_call #func_name #ts_call;
             }
         },
-    };
-
-    tokens
+    }
 }
 
 // port of generate_wrapper, accepts &str inputs in place of tokenstreams
@@ -492,7 +1068,7 @@ fn has_attribute<'a>(field: &'a syn::Field, attr_type: &str) -> Result<&'a syn::
         }
 
         if attr.path.is_ident(attr_type) {
-            return Ok(&attr);
+            return Ok(attr);
         }
     }
     Err(false)
@@ -517,7 +1093,7 @@ fn get_arg_literal(attr: &syn::Attribute, raise_error: bool, error_msg: &str) ->
                         // if a meta is found then it will raise a compiler error to inform the user
                         // it will print out the specified error message
                         if raise_error {
-                            let err = quote::quote_spanned! {m.__span() => compile_error!(error_msg); };
+                            let err = quote::quote_spanned! {m.__span() => compile_error!(#error_msg); };
                             Err(Ok(err))
                         } else {
                             Err(Err(false)) 
@@ -561,14 +1137,152 @@ fn delta_ignore(field: &syn::Field) -> Result<bool, proc_macro2::TokenStream> {
     Ok(is_public(field)) // If there is no delta ignore attribute then we check if it is public value since they are ignored by default
 }
 
-fn get_return(method: &syn::ImplItemMethod) -> String {
-    method.sig.output.clone().into_token_stream().to_string()
+// builds the expression a field is initialized and reset to.
+// uses the expression in `#[delta_default(...)]` if there is one, otherwise the Default of the field's type
+fn delta_default(field: &syn::Field) -> Result<proc_macro2::TokenStream, proc_macro2::TokenStream> {
+    let attr = match has_attribute(field, "delta_default") {
+        Ok(attr) => attr,
+        Err(_) => return Ok(quote::quote! { Default::default() }),
+    };
+
+    // the contents are taken as gospel, they just have to be a single valid expression
+    let expr: syn::Expr = match attr.parse_args() {
+        Ok(expr) => expr,
+        Err(_) => return Err(quote::quote_spanned! {attr.__span() => compile_error!("Argument for `delta_default` must be a valid expression.");}),
+    };
+
+    // string literals are `&str`, so convert them when the field is a `String`
+    let ty = &field.ty;
+    let expr = match &expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(_), .. }) if is_string(ty) => quote::quote_spanned! {expr.__span() => #expr.into() },
+        _ => quote::quote! { #expr },
+    };
+
+    // annotate the type so that a mismatched default is reported on the attribute, not somewhere in the generated code
+    Ok(quote::quote_spanned! {attr.__span() => {
+        let __delta_default: #ty = #expr;
+        __delta_default
+    }})
+}
+
+// the `FieldSchema` of a field, `ty` is the type as it should be shown
+fn field_schema(field: &syn::Field, ty: proc_macro2::TokenStream, reset: &str, required: bool) -> proc_macro2::TokenStream {
+    let name = field.ident.as_ref().map(|n| n.to_string()).unwrap_or_default();
+    let reset = quote::format_ident!("{}", reset);
+    let public = is_public(field);
+    let doc = doc_comment(&field.attrs);
+
+    // the default as the user wrote it, not how it is generated
+    let default = match has_attribute(field, "delta_default").ok().and_then(|attr| attr.parse_args::<syn::Expr>().ok()) {
+        Some(expr) => {
+            let text = expr.__span().source_text().unwrap_or_else(|| expr.to_token_stream().to_string());
+            quote::quote! { Some(#text) }
+        },
+        None => quote::quote! { None },
+    };
+
+    quote::quote! {
+        ::delta_lib::FieldSchema { name: ::std::borrow::Cow::Borrowed(#name), ty: #ty, default: #default, reset: ::delta_lib::ResetPolicy::#reset, public: #public, required: #required, doc: #doc }
+    }
+}
+
+// `///` comments become `#[doc = "..."]` attributes, join them back together one line each
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+    let mut lines = vec![];
+    for attr in attrs.iter().filter(|a| a.path.is_ident("doc")) {
+        if let Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(doc), .. })) = attr.parse_meta() {
+            lines.insert(lines.len(), doc.value().trim().to_owned());
+        }
+    }
+    lines.join("\n")
+}
+
+// fields added by `delta_node_struct`, these are never exposed as inputs
+fn is_generated_field(name: &syn::Ident) -> bool {
+    *name == "__num_fields" || *name == "__set_fields" || *name == "__tick" || *name == "__logs"
+}
+
+fn is_string(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().is_some_and(|seg| seg.ident == "String"),
+        _ => false,
+    }
+}
+
+// if the type is a `Result<T, E>` then get the `T`
+fn result_ok_type(ty: &syn::Type) -> Option<&syn::Type> {
+    if let syn::Type::Path(path) = ty {
+        let seg = path.path.segments.last()?;
+        if seg.ident != "Result" {
+            return None;
+        }
+        if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
+            if let Some(syn::GenericArgument::Type(ok)) = args.args.first() {
+                return Some(ok);
+            }
+        }
+    }
+    None
+}
+
+// if the type is an `Impulse<T>` then get the `T`
+fn impulse_payload(ty: &syn::Type) -> Option<&syn::Type> {
+    if let syn::Type::Path(path) = ty {
+        let seg = path.path.segments.last()?;
+        if seg.ident != "Impulse" {
+            return None;
+        }
+        if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
+            if let Some(syn::GenericArgument::Type(payload)) = args.args.first() {
+                return Some(payload);
+            }
+        }
+    }
+    None
+}
+
+// whether a port of this type carries a `delta_lib::Value`, compares the actual types so another type named `Value` does not count
+fn is_value(ty: &syn::Type) -> proc_macro2::TokenStream {
+    quote::quote! { ::std::any::TypeId::of::<#ty>() == ::std::any::TypeId::of::<::delta_lib::Value>() }
+}
+
+// whether an input of this type takes a `delta_lib::DeltaError`, the same way as `is_value`
+fn is_delta_error(ty: &syn::Type) -> proc_macro2::TokenStream {
+    quote::quote! { ::std::any::TypeId::of::<#ty>() == ::std::any::TypeId::of::<::delta_lib::DeltaError>() }
 }
 
-// TODO: check if the API names are actually correct? 
-fn generate_mappings(args: Vec<syn::NestedMeta>) -> Result<std::collections::HashMap<String, String>, proc_macro2::TokenStream> {
+// readable name of a type for port descriptors, e.g. `Vec<f32>` instead of `Vec < f32 >`
+fn type_name(ty: &syn::Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let mut name = String::new();
+    let mut prev: Option<char> = None;
+    for (i, c) in tokens.char_indices() {
+        // only keep spaces that separate two words, like in `dyn Trait`
+        if c == ' ' {
+            let next = tokens[i + 1..].chars().next();
+            let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            if is_word(prev) && is_word(next) {
+                name.push(c);
+            }
+            continue;
+        }
+        name.push(c);
+        prev = Some(c);
+    }
+    name
+}
+
+fn get_return(method: &syn::ImplItemMethod) -> syn::Type {
+    match &method.sig.output {
+        syn::ReturnType::Default => syn::parse_quote! { () },
+        syn::ReturnType::Type(_, ty) => (**ty).clone(),
+    }
+}
+
+// checks that the API names are valid, `delta_node_impl` checks that the mapped methods exist
+fn generate_mappings(args: &[syn::NestedMeta]) -> Result<std::collections::HashMap<String, syn::LitStr>, proc_macro2::TokenStream> {
     let valid_apis = __delta_hashset_literal!["init", "pre_exec", "on_exec", "post_exec"];
-    let mut map = ::std::collections::HashMap::<String, String>::new();
+    let mut map = ::std::collections::HashMap::<String, syn::LitStr>::new();
 
     for nested_meta in args.iter() {
         match nested_meta {
@@ -578,22 +1292,24 @@ fn generate_mappings(args: Vec<syn::NestedMeta>) -> Result<std::collections::Has
             },
             syn::NestedMeta::Meta(meta) => {
                 if let syn::Meta::NameValue(map_pair) = meta {
-                    let api_name: String;
-                    let cus_name: String;
-
                     // grab the key value pairs and let user know if they messed up
 
                     // check to make sure the identifier is specified correctly
-                    match map_pair.path.get_ident() {
-                        Some(ident) => api_name = ident.to_string(),
+                    let api_name: String = match map_pair.path.get_ident() {
+                        Some(ident) => ident.to_string(),
                         _ => return Err(quote::quote_spanned! {map_pair.__span() => compile_error!("Invalid identifier.")}),
+                    };
+
+                    // the orders are not mappings, `generate_orders` handles those
+                    if api_name == "init_order" || api_name == "post_exec_order" {
+                        continue;
                     }
 
                     // check to make sure the literal after is specified correctly
-                    match &map_pair.lit {
-                        syn::Lit::Str(name) => cus_name = name.value(),
+                    let cus_name: syn::LitStr = match &map_pair.lit {
+                        syn::Lit::Str(name) => name.clone(),
                         _ => return Err(quote::quote_spanned! {map_pair.__span() => compile_error!("Literal must be a String.")}),
-                    }
+                    };
 
                     // check to make sure that we aren't duplicating anything
                     if map.contains_key(&api_name) {
@@ -602,8 +1318,7 @@ fn generate_mappings(args: Vec<syn::NestedMeta>) -> Result<std::collections::Has
 
                     // next check to make sure that it is a vaid api token
                     if !valid_apis.contains(&*api_name) {
-                        // TODO: figure out how to format 
-                        return Err(quote::quote_spanned! {map_pair.__span() => compile_error!("Invalid Delta API, must be one of: {`init`, `pre_exec`, `on_exec`, `post_exec`}", 0)});
+                        return Err(quote::quote_spanned! {map_pair.__span() => compile_error!("Invalid Delta API, must be one of: `init`, `pre_exec`, `on_exec`, `post_exec`.")});
                     }
 
                     // if all check pass then add it to the map
@@ -0,0 +1,13 @@
+use std::process::Command;
+
+// plugins have to be built by the same compiler as the engine loading them, so remember which one this was
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let version = Command::new(rustc).arg("--version").output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    println!("cargo:rustc-env=DELTA_RUSTC_VERSION={}", version.trim());
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,79 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorKind {
+    DivisionByZero,
+    Overflow,
+    Parse,
+    InvalidInput,
+    IterationLimit, // a loop ran for longer than it is allowed to
+    #[default]
+    Other,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ErrorKind::DivisionByZero => "division by zero",
+            ErrorKind::Overflow => "overflow",
+            ErrorKind::Parse => "parse error",
+            ErrorKind::InvalidInput => "invalid input",
+            ErrorKind::IterationLimit => "iteration limit",
+            ErrorKind::Other => "error",
+        })
+    }
+}
+
+// an error a node hit while executing, sent as `Impulse::ERROR` instead of panicking.
+// an on_exec that returns `Result<Impulse<T>, E>` has its errors converted with `Into<DeltaError>`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeltaError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub node: Option<usize>, // filled in by the engine
+}
+
+impl DeltaError {
+    pub fn new<S: Into<String>>(kind: ErrorKind, message: S) -> DeltaError {
+        DeltaError { kind, message: message.into(), node: None }
+    }
+}
+
+impl std::fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.node {
+            Some(node) => write!(f, "node #{} {}: {}", node, self.kind, self.message),
+            None => write!(f, "{}: {}", self.kind, self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
+impl From<&str> for DeltaError {
+    fn from(message: &str) -> Self {
+        DeltaError::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<String> for DeltaError {
+    fn from(message: String) -> Self {
+        DeltaError::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<std::num::ParseIntError> for DeltaError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        DeltaError::new(ErrorKind::Parse, e.to_string())
+    }
+}
+
+impl From<std::num::ParseFloatError> for DeltaError {
+    fn from(e: std::num::ParseFloatError) -> Self {
+        DeltaError::new(ErrorKind::Parse, e.to_string())
+    }
+}
+
+impl From<std::num::TryFromIntError> for DeltaError {
+    fn from(e: std::num::TryFromIntError) -> Self {
+        DeltaError::new(ErrorKind::Overflow, e.to_string())
+    }
+}
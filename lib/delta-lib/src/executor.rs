@@ -0,0 +1,111 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ Context, Poll, Wake, Waker };
+use std::thread::{ self, Thread };
+
+// a boxed future borrowing whatever it runs on, e.g. the node it executes
+pub type DeltaFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+// wakes the thread that is blocked on the future
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// a tiny executor, runs the future on the current thread until it is done.
+// there is no reactor, so whatever the future waits on has to wake it from somewhere else (e.g. another thread)
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(), // unparking early just means polling again
+        }
+    }
+}
+
+// run every future at the same time, returns the outputs in the same order as the futures
+pub fn join_all<T>(futures: Vec<DeltaFuture<'_, T>>) -> Vec<T> {
+    let mut futures: Vec<Option<DeltaFuture<'_, T>>> = futures.into_iter().map(Some).collect();
+    let mut outputs: Vec<Option<T>> = futures.iter().map(|_| None).collect();
+
+    block_on(std::future::poll_fn(|cx| {
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if let Some(f) = future {
+                if let Poll::Ready(x) = f.as_mut().poll(cx) {
+                    *output = Some(x);
+                    *future = None;
+                }
+            }
+        }
+
+        if futures.iter().all(Option::is_none) {
+            Poll::Ready(outputs.iter_mut().filter_map(Option::take).collect())
+        } else {
+            Poll::Pending
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{ Arc, Mutex };
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use std::task::{ Context, Poll };
+    use crate::executor::{ block_on, join_all, DeltaFuture };
+
+    // ready after being polled `polls` more times, keeps count of how many of them have started but not finished
+    struct Countdown {
+        polls: u64,
+        started: bool,
+        in_flight: Arc<AtomicUsize>,
+        most: Arc<AtomicUsize>, // the most that were in flight at once
+        log: Arc<Mutex<Vec<u64>>>,
+        id: u64,
+    }
+
+    impl Future for Countdown {
+        type Output = u64;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u64> {
+            if !self.started {
+                self.started = true;
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most.fetch_max(now, Ordering::SeqCst);
+            }
+            if self.polls == 0 {
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                self.log.lock().unwrap().push(self.id);
+                return Poll::Ready(self.id);
+            }
+            self.polls -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn join() {
+        let (in_flight, most, log) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)), Arc::new(Mutex::new(vec![])));
+        let futures: Vec<DeltaFuture<'_, u64>> = (0..3).map(|id| {
+            let countdown = Countdown { polls: 2 - id, started: false, in_flight: in_flight.clone(), most: most.clone(), log: log.clone(), id };
+            Box::pin(countdown) as DeltaFuture<'_, u64>
+        }).collect();
+
+        // outputs stay in order even though the futures finish in reverse
+        assert_eq!(vec![0, 1, 2], join_all(futures));
+        assert_eq!(vec![2, 1, 0], *log.lock().unwrap());
+        // all of them were waiting at once
+        assert_eq!(3, most.load(Ordering::SeqCst));
+        assert_eq!(4, block_on(async { 2 + 2 }));
+    }
+}
@@ -1,16 +1,57 @@
+mod error;
+mod executor;
+mod message;
+mod overflow;
+mod plugin;
+mod ports;
+mod registry;
+mod schema;
+mod value;
+pub use error::{ DeltaError, ErrorKind };
+pub use executor::{ DeltaFuture, block_on, join_all };
+pub use message::{ DeltaMessage, LogLevel };
+pub use overflow::Overflow;
+pub use plugin::{ PLUGIN_ABI_VERSION, PLUGIN_BUILD_INFO };
+pub use ports::{ DeltaPorts, DynDeltaNode, PortDescriptor, PortKind, PortError, SendNode, SetError, input_ports, output_ports };
+pub use registry::{ NodeFactory, NodeRegistry, RegisteredNode, RegistryError };
+pub use schema::{ FieldSchema, NodeSchema, ResetPolicy };
+pub use value::{ Opaque, Tensor, ToValue, Value, ValueError, ViaFrom, ViaOpaque };
+
 // is this even necessary??? I am leaning towards no...
 // but it does provide a standard interface and generics where required, so I guess it works?
 pub trait DeltaNode<ReturnType, NodeType> { // is there a better naming convention?
-    fn __execute(self) -> ReturnType;
+    // does not consume the node, so any state kept between executions (e.g. `delta_noreset` fields) survives
+    fn __execute(&mut self) -> ReturnType;
     fn __initialize() -> Box<NodeType>;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Impulse<T> {
-    NOOP, //no op
+    #[default]
+    NOOP, //no op, output ports start out (and are reset to) NOOP
     SEND(T), // pass message
-    TICK, // step tell engine to step forward
-    //LOG(DeltaMessage), //
+    TICK, // step tell engine to step forward, the node runs again on the next tick when the graph is clocked
+    LOG(DeltaMessage), // only a log message, no data. Use `__log` to log next to sending data
+    ERROR(DeltaError), // the node failed, what happens next is up to the engine's error policy
+}
+
+impl<T> Impulse<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Impulse<U> {
+        match self {
+            Impulse::NOOP => Impulse::NOOP,
+            Impulse::SEND(x) => Impulse::SEND(f(x)),
+            Impulse::TICK => Impulse::TICK,
+            Impulse::LOG(message) => Impulse::LOG(message),
+            Impulse::ERROR(error) => Impulse::ERROR(error),
+        }
+    }
+}
+
+impl<T: std::any::Any + Send + Sync> Impulse<T> {
+    // the payload as a `Value`, which is how impulses from different nodes are handled together (see `Value::from_any`)
+    pub fn into_value(self) -> Impulse<Value> {
+        self.map(Value::from_any)
+    }
 }
 
 #[cfg(test)]
@@ -28,4 +69,28 @@ mod tests {
         let vec_send: Impulse<&Vec<f32>> = Impulse::SEND(&my_vec);
         assert_eq!(vec_send, Impulse::<&Vec<f32>>::SEND(&my_vec));
     }
+
+    #[test]
+    fn messages() {
+        use crate::{ DeltaMessage, LogLevel };
+
+        let message = DeltaMessage::new(LogLevel::Warn, "overflow").with_field("x", 1).with_field("y", "big");
+        assert_eq!("[WARN tick 0] overflow x=1 y=big", message.to_string());
+
+        let log: Impulse<i32> = Impulse::LOG(message.clone());
+        assert_eq!(Impulse::<String>::LOG(message), log.map(|x| x.to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        use crate::{ DeltaError, ErrorKind };
+
+        let parsed: Result<i32, DeltaError> = "abc".parse::<i32>().map_err(DeltaError::from);
+        assert_eq!(ErrorKind::Parse, parsed.unwrap_err().kind);
+
+        let mut error = DeltaError::new(ErrorKind::DivisionByZero, "y was 0");
+        assert_eq!("division by zero: y was 0", error.to_string());
+        error.node = Some(2);
+        assert_eq!("node #2 division by zero: y was 0", error.to_string());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        })
+    }
+}
+
+// a log message from a node.
+// nodes log with `self.__log(...)` (which fills in the tick) or by returning `Impulse::LOG`,
+// the engine fills in which node it came from when it collects the message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeltaMessage {
+    pub level: LogLevel,
+    pub node: Option<usize>,
+    pub tick: u64,
+    pub text: String,
+    pub fields: Vec<(String, String)>, // extra key / value pairs, kept in the order they were added
+}
+
+impl DeltaMessage {
+    pub fn new<S: Into<String>>(level: LogLevel, text: S) -> DeltaMessage {
+        DeltaMessage { level, node: None, tick: 0, text: text.into(), fields: vec![] }
+    }
+
+    pub fn with_field<K: Into<String>, V: ToString>(mut self, key: K, value: V) -> DeltaMessage {
+        self.fields.push((key.into(), value.to_string()));
+        self
+    }
+}
+
+impl std::fmt::Display for DeltaMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} tick {}", self.level, self.tick)?;
+        if let Some(node) = self.node {
+            write!(f, " node #{}", node)?;
+        }
+        write!(f, "] {}", self.text)?;
+        for (key, value) in self.fields.iter() {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use std::convert::TryFrom;
+use crate::{ DeltaError, ErrorKind, Value, ValueError };
+
+// what a node does when integer math overflows. Written as "checked", "wrapping" or "saturating" when it is a `Value`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Overflow {
+    #[default]
+    Checked,    // send an `ErrorKind::Overflow` error
+    Wrapping,   // wrap around, like `wrapping_add`
+    Saturating, // stop at the smallest or largest value, like `saturating_add`
+}
+
+impl Overflow {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overflow::Checked => "checked",
+            Overflow::Wrapping => "wrapping",
+            Overflow::Saturating => "saturating",
+        }
+    }
+}
+
+impl std::str::FromStr for Overflow {
+    type Err = DeltaError;
+
+    fn from_str(s: &str) -> Result<Overflow, DeltaError> {
+        match s {
+            "checked" => Ok(Overflow::Checked),
+            "wrapping" => Ok(Overflow::Wrapping),
+            "saturating" => Ok(Overflow::Saturating),
+            _ => Err(DeltaError::new(ErrorKind::Parse, format!("`{}` is not an overflow mode, expected checked, wrapping or saturating", s))),
+        }
+    }
+}
+
+impl From<Overflow> for Value {
+    fn from(x: Overflow) -> Value {
+        Value::String(x.name().to_owned())
+    }
+}
+
+impl TryFrom<Value> for Overflow {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<Overflow, ValueError> {
+        let x = match &value {
+            Value::String(x) => x.parse().ok(),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "Overflow", found: value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ Overflow, Value };
+
+    #[test]
+    fn values() {
+        assert_eq!(Ok(Overflow::Wrapping), Value::from_any(Overflow::Wrapping).cast::<Overflow>());
+        assert_eq!(Ok(Overflow::Checked), Value::from("checked").cast::<Overflow>());
+        assert!(Value::from("wrap").cast::<Overflow>().is_err());
+        assert_eq!(Value::String("saturating".to_owned()), Value::from(Overflow::Saturating));
+    }
+}
@@ -0,0 +1,36 @@
+// bumped by hand whenever anything a plugin and the engine share changes shape: `NodeRegistry`, `RegisteredNode`,
+// the `DynDeltaNode`, `DeltaPorts` and `SendNode` traits and every type in their signatures (`Value`, `Impulse`, `PortDescriptor`, ...).
+// the build info below only catches a different compiler or delta-lib release, not a change made in between releases
+pub const PLUGIN_ABI_VERSION: u32 = 8;
+
+// node types are passed between the plugin and the engine as rust trait objects, which only works if both sides
+// were built by the same compiler against the same delta-lib. Nul terminated so it can cross the C ABI
+pub const PLUGIN_BUILD_INFO: &str = concat!("rustc ", env!("DELTA_RUSTC_VERSION"), "; delta-lib ", env!("CARGO_PKG_VERSION"), "\0");
+
+// turns a node crate into a plugin, list every node type the crate exports: `delta_plugin!(Addi32, Subi32);`
+// generates the functions the engine looks up after opening the library. They are only exported under those names
+// when the node crate's `plugin` feature is on, so several node crates can be linked into the same binary with it off
+#[macro_export]
+macro_rules! delta_plugin {
+    ($($node:ty),* $(,)?) => {
+        #[cfg_attr(feature = "plugin", no_mangle)]
+        pub extern "C" fn __delta_plugin_abi_version() -> u32 {
+            $crate::PLUGIN_ABI_VERSION
+        }
+
+        #[cfg_attr(feature = "plugin", no_mangle)]
+        pub extern "C" fn __delta_plugin_build_info() -> *const ::std::os::raw::c_char {
+            $crate::PLUGIN_BUILD_INFO.as_ptr() as *const ::std::os::raw::c_char
+        }
+
+        // only called once the abi version and build info match. This is a rust ABI function that gets a `&mut NodeRegistry`
+        // from the engine and fills it with trait objects whose vtables live in the plugin. Rust makes no promises about
+        // the layout of any of that, so it is only sound because both sides were built from the same delta-lib by the
+        // same compiler, and because `PLUGIN_ABI_VERSION` is bumped whenever those types change.
+        // also works without loading the crate as a plugin, to register all of its nodes at once
+        #[cfg_attr(feature = "plugin", no_mangle)]
+        pub fn __delta_plugin_register(registry: &mut $crate::NodeRegistry) {
+            $( <$node>::__register(registry); )*
+        }
+    };
+}
@@ -0,0 +1,208 @@
+use std::any::Any;
+use std::borrow::Cow;
+use crate::{ DeltaFuture, DeltaMessage, Impulse, NodeSchema, Value };
+
+// which side of a node a port is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+    Input,  // set by the engine before executing, backed by a `__set_*` function
+    Output, // filled by the node while executing
+}
+
+// describes a single port of a node, generated by `RegisterDeltaNode`
+// an engine wires edges using these instead of having to know the names of generated methods
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortDescriptor {
+    pub name: Cow<'static, str>, // borrowed for generated nodes, owned for nodes only known at runtime
+    pub ty: &'static str, // the type of the data the port carries, as it was written in the node
+    pub index: usize,     // position of the port among the other ports of the same kind
+    pub kind: PortKind,
+    pub required: bool,   // inputs without a `delta_default` have to be set before the node is ready, always false for outputs
+    pub dynamic: bool,    // the port carries a `delta_lib::Value`, so it can be connected to a port of any type
+    pub error: bool,      // the input takes a `delta_lib::DeltaError`, so errors can be routed to it. Always false for outputs
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortError {
+    UnknownPort(usize),
+    TypeMismatch { port: String, expected: &'static str, found: &'static str }, // `found` is the kind of the value
+}
+
+impl std::fmt::Display for PortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortError::UnknownPort(index) => write!(f, "node has no input port {}", index),
+            PortError::TypeMismatch { port, expected, found } => write!(f, "input port `{}` expects a value of type `{}`, got a {}", port, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for PortError {}
+
+// why setting a field by name failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetError {
+    UnknownField(String),
+    NotAnInput(String), // the field exists, but it is `delta_ignore`d or an output
+    TypeMismatch { field: String, expected: &'static str, found: Value },
+}
+
+impl std::fmt::Display for SetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetError::UnknownField(field) => write!(f, "there is no field named `{}`", field),
+            SetError::NotAnInput(field) => write!(f, "`{}` is not an input, so it can not be set", field),
+            SetError::TypeMismatch { field, expected, found } => write!(f, "`{}` expects a `{}`, got the {} {:?}", field, expected, found.kind(), found),
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+// port level access to a node, generated by `RegisterDeltaNode`
+// the on_execute return value is always output port 0 named `out`, followed by each `#[delta_output]` field
+pub trait DeltaPorts {
+    fn __ports() -> Vec<PortDescriptor>;
+
+    // the name the node is registered under, e.g. `arithmetic::Addi32`
+    fn __node_type() -> &'static str;
+
+    fn __schema() -> NodeSchema;
+
+    // set the input port at `index`, the value is converted to the type of the port with `Value::cast`.
+    // the value is cloned into the node, so one output can feed any number of inputs
+    fn __set_port(&mut self, index: usize, value: &Value) -> Result<(), PortError>;
+
+    // reset the input port at `index` as if it was never set, a required input has to be set again before the node is ready
+    fn __unset_port(&mut self, index: usize);
+
+    // set an input by name, converting the value to the type of the input
+    fn __set_named(&mut self, field: &str, value: Value) -> Result<(), SetError>;
+
+    // true once every required input is set
+    fn __is_ready(&self) -> bool;
+
+    // let the node know which step the engine is on
+    fn __set_tick(&mut self, tick: u64);
+
+    // everything the node logged with `__log` since the last call
+    fn __take_logs(&mut self) -> Vec<DeltaMessage>;
+
+    // run the node, then collect every output port in port order. Output fields are reset to NOOP as they are collected.
+    // whatever a node sends is turned into a `Value`, a type that `Value` has no variant for becomes `Opaque` and has to be `Send + Sync`
+    fn __execute_ports(&mut self) -> Vec<Impulse<Value>>;
+
+    // same as `__execute_ports` but without blocking on async hooks, so an engine can run several nodes at once.
+    // for a node without async hooks the whole execution happens the first time the future is polled
+    fn __execute_ports_async(&mut self) -> DeltaFuture<'_, Vec<Impulse<Value>>>;
+
+    // the node as something that can be executed on another thread,
+    // None if the node is marked `#[delta_thread_local]`
+    fn __send_view(&mut self) -> Option<&mut dyn SendNode>;
+}
+
+// a node that can be executed on a worker thread, generated by `RegisterDeltaNode` unless the node is `#[delta_thread_local]`.
+// requires the node to be `Send`
+pub trait SendNode: Send {
+    // same as `DeltaPorts::__execute_ports`
+    fn __execute_send(&mut self) -> Vec<Impulse<Value>>;
+}
+
+pub fn input_ports(ports: &[PortDescriptor]) -> impl Iterator<Item = &PortDescriptor> {
+    ports.iter().filter(|p| p.kind == PortKind::Input)
+}
+
+pub fn output_ports(ports: &[PortDescriptor]) -> impl Iterator<Item = &PortDescriptor> {
+    ports.iter().filter(|p| p.kind == PortKind::Output)
+}
+
+// object safe version of a node, so that any mix of node types can be kept in one `Vec<Box<dyn DynDeltaNode>>`.
+// implemented for every node that derives `RegisterDeltaNode`
+pub trait DynDeltaNode {
+    // the name the node type is registered under, see `NodeRegistry`
+    fn __type_name(&self) -> &str;
+
+    fn __port_list(&self) -> Vec<PortDescriptor>;
+
+    fn __describe_dyn(&self) -> NodeSchema;
+
+    fn __set_input(&mut self, index: usize, value: &Value) -> Result<(), PortError>;
+
+    fn __unset_input(&mut self, index: usize);
+
+    fn __set_by_name_dyn(&mut self, field: &str, value: Value) -> Result<(), SetError>;
+
+    fn __ready(&self) -> bool;
+
+    fn __set_clock(&mut self, tick: u64);
+
+    fn __drain_logs(&mut self) -> Vec<DeltaMessage>;
+
+    fn __execute_dyn(&mut self) -> Vec<Impulse<Value>>;
+
+    fn __execute_dyn_async(&mut self) -> DeltaFuture<'_, Vec<Impulse<Value>>>;
+
+    fn __as_send(&mut self) -> Option<&mut dyn SendNode>;
+
+    // get back to the concrete node type
+    fn __as_any(&self) -> &dyn Any;
+    fn __as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<N: DeltaPorts + 'static> DynDeltaNode for N {
+    fn __type_name(&self) -> &str {
+        N::__node_type()
+    }
+
+    fn __port_list(&self) -> Vec<PortDescriptor> {
+        N::__ports()
+    }
+
+    fn __describe_dyn(&self) -> NodeSchema {
+        N::__schema()
+    }
+
+    fn __set_input(&mut self, index: usize, value: &Value) -> Result<(), PortError> {
+        self.__set_port(index, value)
+    }
+
+    fn __unset_input(&mut self, index: usize) {
+        self.__unset_port(index)
+    }
+
+    fn __set_by_name_dyn(&mut self, field: &str, value: Value) -> Result<(), SetError> {
+        self.__set_named(field, value)
+    }
+
+    fn __ready(&self) -> bool {
+        self.__is_ready()
+    }
+
+    fn __set_clock(&mut self, tick: u64) {
+        self.__set_tick(tick)
+    }
+
+    fn __drain_logs(&mut self) -> Vec<DeltaMessage> {
+        self.__take_logs()
+    }
+
+    fn __execute_dyn(&mut self) -> Vec<Impulse<Value>> {
+        self.__execute_ports()
+    }
+
+    fn __execute_dyn_async(&mut self) -> DeltaFuture<'_, Vec<Impulse<Value>>> {
+        self.__execute_ports_async()
+    }
+
+    fn __as_send(&mut self) -> Option<&mut dyn SendNode> {
+        self.__send_view()
+    }
+
+    fn __as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn __as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use crate::{ DynDeltaNode, NodeSchema, PortDescriptor };
+
+// creates a fresh node, already initialized. Gets the registry the node is created from,
+// so a node made out of other nodes (like a composite) can create those too, and fails if it can not
+pub type NodeFactory = Box<dyn Fn(&NodeRegistry) -> Result<Box<dyn DynDeltaNode>, RegistryError> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    UnknownNode(String),
+    CreateFailed { node: String, error: String }, // the factory of a registered type failed
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::UnknownNode(name) => write!(f, "no node type named `{}` is registered", name),
+            RegistryError::CreateFailed { node, error } => write!(f, "a `{}` could not be created, {}", node, error),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+// a node type that can be created by name
+pub struct RegisteredNode {
+    pub name: String, // full path of the node type, e.g. `arithmetic::Addi32`
+    pub ports: Vec<PortDescriptor>,
+    pub schema: NodeSchema,
+    factory: NodeFactory,
+}
+
+impl RegisteredNode {
+    pub fn create(&self, registry: &NodeRegistry) -> Result<Box<dyn DynDeltaNode>, RegistryError> {
+        (self.factory)(registry)
+    }
+}
+
+// every node type that can be created by name, e.g. by a program loader or an editor.
+// nodes add themselves with the `__register` function generated by `RegisterDeltaNode`: `Addi32::__register(&mut registry)`
+#[derive(Default)]
+pub struct NodeRegistry {
+    nodes: BTreeMap<String, RegisteredNode>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> NodeRegistry {
+        NodeRegistry::default()
+    }
+
+    // registering a name again replaces the old factory
+    pub fn register<S: Into<String>>(&mut self, name: S, ports: Vec<PortDescriptor>, schema: NodeSchema, factory: NodeFactory) {
+        let name = name.into();
+        self.nodes.insert(name.clone(), RegisteredNode { name, ports, schema, factory });
+    }
+
+    pub fn create(&self, name: &str) -> Result<Box<dyn DynDeltaNode>, RegistryError> {
+        self.get(name).ok_or_else(|| RegistryError::UnknownNode(name.to_owned()))?.create(self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredNode> {
+        self.nodes.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.nodes.contains_key(name)
+    }
+
+    // sorted by name
+    pub fn nodes(&self) -> impl Iterator<Item = &RegisteredNode> {
+        self.nodes.values()
+    }
+
+    // move everything registered in `other` into this registry
+    pub fn extend(&mut self, other: NodeRegistry) {
+        self.nodes.extend(other.nodes);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
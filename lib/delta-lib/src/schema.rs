@@ -0,0 +1,43 @@
+use std::borrow::Cow;
+
+// what happens to a field's value between executions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetPolicy {
+    Reset,   // back to its default after every execution (outputs go back to NOOP once they are collected)
+    NoReset, // `delta_noreset`, keeps its value until it is set again
+    Never,   // `delta_ignore`, the engine never touches it
+}
+
+// one field of a node, generated by `RegisterDeltaNode`.
+// names are borrowed for generated nodes and owned for nodes only known at runtime, like composites
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: Cow<'static, str>,
+    pub ty: &'static str, // the rust type, for outputs the `T` of the `Impulse<T>`
+    pub default: Option<&'static str>, // the `delta_default` expression as it was written
+    pub reset: ResetPolicy,
+    pub public: bool,
+    pub required: bool, // for inputs, whether the node waits for it before it can run
+    pub doc: &'static str, // the `///` comments on the field, one line each
+}
+
+// everything the derive knows about a node, for editors, validators and the like to show its signature.
+// `inputs` and `outputs` are in port order, `state` are the `delta_ignore` fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeSchema {
+    pub name: Cow<'static, str>, // the name the node is registered under
+    pub doc: Cow<'static, str>,
+    pub inputs: Vec<FieldSchema>,
+    pub outputs: Vec<FieldSchema>,
+    pub state: Vec<FieldSchema>,
+}
+
+impl NodeSchema {
+    pub fn input(&self, name: &str) -> Option<&FieldSchema> {
+        self.inputs.iter().find(|f| f.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&FieldSchema> {
+        self.outputs.iter().find(|f| f.name == name)
+    }
+}
@@ -0,0 +1,585 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use crate::Overflow;
+
+// the data that travels between nodes. Nodes keep their own types, their ports convert to and from a Value,
+// so any mix of nodes can be wired together and a program can give an input a value without knowing its rust type.
+// every integer that fits in an `i64` is an `Int`, `UInt` is only used above `i64::MAX`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Unit, // `()`, e.g. what a node that only ticks sends
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    Tensor(Tensor),
+    Opaque(Opaque), // any other type, passed along as it is
+}
+
+// an n-dimensional array of floats, stored row major
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tensor {
+    shape: Vec<usize>,
+    data: Vec<f64>,
+}
+
+impl Tensor {
+    // None if the data does not fill the shape exactly
+    pub fn new(shape: Vec<usize>, data: Vec<f64>) -> Option<Tensor> {
+        if shape.iter().product::<usize>() != data.len() {
+            return None;
+        }
+        Some(Tensor { shape, data })
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<f64> {
+        self.data
+    }
+
+    // the element at `index`, which needs one position per dimension
+    pub fn get(&self, index: &[usize]) -> Option<f64> {
+        if index.len() != self.shape.len() {
+            return None;
+        }
+        let mut offset = 0;
+        for (i, n) in index.iter().zip(self.shape.iter()) {
+            if i >= n {
+                return None;
+            }
+            offset = offset * n + i;
+        }
+        self.data.get(offset).copied()
+    }
+}
+
+// a value of a type that `Value` has no variant for. It is shared instead of copied when it is sent to several inputs,
+// so two opaque values are only equal if they are the same value
+#[derive(Clone)]
+pub struct Opaque {
+    type_name: &'static str,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+impl Opaque {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Opaque {
+        Opaque { type_name: std::any::type_name::<T>(), value: Arc::new(value) }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref::<T>()
+    }
+}
+
+impl PartialEq for Opaque {
+    fn eq(&self, other: &Opaque) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl std::fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Opaque({})", self.type_name)
+    }
+}
+
+// a value that could not be converted to the type it was needed as
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError {
+    pub expected: &'static str,
+    pub found: Value,
+}
+
+impl std::fmt::Display for ValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected a `{}`, found the {} {:?}", self.expected, self.found.kind(), self.found)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+// calls `$known!` with every type that `Value` converts to and from
+macro_rules! each_known_type {
+    ($known:ident) => {
+        $known!((), bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, char, String, Tensor);
+        $known!(Vec<()>, Vec<bool>, Vec<i8>, Vec<i16>, Vec<i32>, Vec<i64>, Vec<isize>, Vec<u8>, Vec<u16>, Vec<u32>, Vec<u64>, Vec<usize>,
+            Vec<f32>, Vec<f64>, Vec<char>, Vec<String>, Vec<Tensor>, Vec<Value>);
+        $known!(BTreeMap<String, bool>, BTreeMap<String, i8>, BTreeMap<String, i16>, BTreeMap<String, i32>, BTreeMap<String, i64>,
+            BTreeMap<String, isize>, BTreeMap<String, u8>, BTreeMap<String, u16>, BTreeMap<String, u32>, BTreeMap<String, u64>,
+            BTreeMap<String, usize>, BTreeMap<String, f32>, BTreeMap<String, f64>, BTreeMap<String, char>, BTreeMap<String, String>,
+            BTreeMap<String, Tensor>, BTreeMap<String, Value>);
+    };
+}
+
+impl Value {
+    // what kind of value this is, for error messages. Opaque values give the name of their type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Unit => "unit",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::UInt(_) => "uint",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Tensor(_) => "tensor",
+            Value::Opaque(opaque) => opaque.type_name(),
+        }
+    }
+
+    // wrap any value, types with a `From` conversion get their own variant and everything else becomes `Opaque`.
+    // this checks the type at run time, code that knows the type should use `ToValue` instead
+    pub fn from_any<T: Any + Send + Sync>(value: T) -> Value {
+        let type_name = std::any::type_name::<T>();
+        let mut value: Box<dyn Any + Send + Sync> = Box::new(value);
+
+        macro_rules! known {
+            ($($t:ty),*) => {
+                $(
+                    value = match value.downcast::<$t>() {
+                        Ok(x) => return Value::from(*x),
+                        Err(value) => value,
+                    };
+                )*
+            };
+        }
+
+        known!(Value, Opaque);
+        each_known_type!(known);
+
+        Value::Opaque(Opaque { type_name, value: Arc::from(value) })
+    }
+
+    // convert to a rust type without losing anything, the value is given back if that is not possible.
+    // works for every type that `Value` has a lossless `TryFrom` for, and for opaque values of the type
+    pub fn cast<T: Any + Clone>(self) -> Result<T, Value> {
+        if let Value::Opaque(opaque) = &self {
+            if let Some(x) = opaque.downcast_ref::<T>() {
+                return Ok(x.clone());
+            }
+        }
+
+        let mut out: Option<T> = None;
+        match convert(self, &mut out) {
+            Some(value) => Err(value),
+            None => Ok(out.expect("convert fills in the output when it succeeds")),
+        }
+    }
+
+    // same as `cast`, but numbers convert to any number type the way `as` does: floats are truncated,
+    // out of range values saturate (floats to integers) or wrap (integers to integers) and bools are 0 or 1
+    pub fn cast_lossy<T: Any + Clone>(self) -> Result<T, Value> {
+        let mut out: Option<T> = None;
+        match convert_lossy(self, &mut out) {
+            Some(value) => value.cast::<T>(),
+            None => Ok(out.expect("convert_lossy fills in the output when it succeeds")),
+        }
+    }
+}
+
+// fills in `out`, an `Option<T>`, with the value converted to T. Gives the value back if it could not be converted
+fn convert(value: Value, out: &mut dyn Any) -> Option<Value> {
+    macro_rules! known {
+        ($($t:ty),*) => {
+            $(
+                if let Some(out) = out.downcast_mut::<Option<$t>>() {
+                    match <$t>::try_from(value) {
+                        Ok(x) => *out = Some(x),
+                        Err(error) => return Some(error.found),
+                    }
+                    return None;
+                }
+            )*
+        };
+    }
+
+    if let Some(out) = out.downcast_mut::<Option<Value>>() {
+        *out = Some(value);
+        return None;
+    }
+    each_known_type!(known);
+    // not a type `Value` knows, but settings like it are read from the string they are written as
+    known!(Overflow);
+
+    Some(value)
+}
+
+fn convert_lossy(value: Value, out: &mut dyn Any) -> Option<Value> {
+    macro_rules! number {
+        ($($t:ty),*) => {
+            $(
+                if let Some(out) = out.downcast_mut::<Option<$t>>() {
+                    *out = Some(match value {
+                        Value::Bool(x) => u8::from(x) as $t,
+                        Value::Int(x) => x as $t,
+                        Value::UInt(x) => x as $t,
+                        Value::Float(x) => x as $t,
+                        _ => return Some(value),
+                    });
+                    return None;
+                }
+            )*
+        };
+    }
+
+    number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+    Some(value)
+}
+
+macro_rules! from_int {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(x: $t) -> Value {
+                    match i64::try_from(x) {
+                        Ok(x) => Value::Int(x),
+                        Err(_) => Value::UInt(x as u64),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<()> for Value {
+    fn from(_: ()) -> Value {
+        Value::Unit
+    }
+}
+
+impl From<bool> for Value {
+    fn from(x: bool) -> Value {
+        Value::Bool(x)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(x: f32) -> Value {
+        Value::Float(f64::from(x))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Value {
+        Value::Float(x)
+    }
+}
+
+impl From<char> for Value {
+    fn from(x: char) -> Value {
+        Value::String(x.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(x: String) -> Value {
+        Value::String(x)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(x: &str) -> Value {
+        Value::String(x.to_owned())
+    }
+}
+
+impl From<Tensor> for Value {
+    fn from(x: Tensor) -> Value {
+        Value::Tensor(x)
+    }
+}
+
+impl From<Opaque> for Value {
+    fn from(x: Opaque) -> Value {
+        Value::Opaque(x)
+    }
+}
+
+// use `Value::Bytes` for bytes, this makes a list of integers
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(x: Vec<T>) -> Value {
+        Value::List(x.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(x: BTreeMap<String, T>) -> Value {
+        Value::Map(x.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+// picks how a value becomes a `Value` from its type when the code is compiled: `(&&ToValue::new(x)).to_value()`
+// uses the `From` conversion if there is one and makes an `Opaque` otherwise, so only opaque types have to be `Send + Sync`.
+// both `ViaFrom` and `ViaOpaque` have to be in scope, and the type of `x` has to be known (in generic code it is always opaque)
+pub struct ToValue<T>(Cell<Option<T>>);
+
+impl<T> ToValue<T> {
+    pub fn new(value: T) -> ToValue<T> {
+        ToValue(Cell::new(Some(value)))
+    }
+
+    fn take(&self) -> T {
+        self.0.take().expect("a `ToValue` is only converted once")
+    }
+}
+
+pub trait ViaFrom {
+    fn to_value(&self) -> Value;
+}
+
+impl<T: Into<Value>> ViaFrom for &ToValue<T> {
+    fn to_value(&self) -> Value {
+        self.take().into()
+    }
+}
+
+pub trait ViaOpaque {
+    fn to_value(&self) -> Value;
+}
+
+impl<T: Any + Send + Sync> ViaOpaque for ToValue<T> {
+    fn to_value(&self) -> Value {
+        Value::Opaque(Opaque::new(self.take()))
+    }
+}
+
+// the lossless conversions. Integers convert to any integer type they fit in, and to floats that can hold them exactly.
+// floats convert to integers only if they are a whole number. Narrowing a float to `f32` rounds to the nearest `f32`
+// (the same as writing it as an `f32` literal) but fails if it is out of range
+macro_rules! try_int {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<Value> for $t {
+                type Error = ValueError;
+
+                fn try_from(value: Value) -> Result<$t, ValueError> {
+                    let x = match &value {
+                        Value::Int(x) => <$t>::try_from(*x).ok(),
+                        Value::UInt(x) => <$t>::try_from(*x).ok(),
+                        Value::Float(x) if x.fract() == 0.0 && (*x as i128) as f64 == *x => <$t>::try_from(*x as i128).ok(),
+                        _ => None,
+                    };
+                    x.ok_or(ValueError { expected: stringify!($t), found: value })
+                }
+            }
+        )*
+    };
+}
+
+try_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl TryFrom<Value> for f64 {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<f64, ValueError> {
+        let x = match &value {
+            Value::Float(x) => Some(*x),
+            Value::Int(x) => Some(*x as f64).filter(|y| *y as i128 == i128::from(*x)),
+            Value::UInt(x) => Some(*x as f64).filter(|y| *y as i128 == i128::from(*x)),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "f64", found: value })
+    }
+}
+
+impl TryFrom<Value> for f32 {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<f32, ValueError> {
+        let x = match &value {
+            Value::Float(x) => Some(*x as f32).filter(|y| y.is_finite() || !x.is_finite()),
+            Value::Int(x) => Some(*x as f32).filter(|y| *y as i128 == i128::from(*x)),
+            Value::UInt(x) => Some(*x as f32).filter(|y| *y as i128 == i128::from(*x)),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "f32", found: value })
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<(), ValueError> {
+        match value {
+            Value::Unit => Ok(()),
+            found => Err(ValueError { expected: "()", found }),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<bool, ValueError> {
+        match value {
+            Value::Bool(x) => Ok(x),
+            found => Err(ValueError { expected: "bool", found }),
+        }
+    }
+}
+
+// a string with exactly one character
+impl TryFrom<Value> for char {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<char, ValueError> {
+        let x = match &value {
+            Value::String(x) if x.chars().count() == 1 => x.chars().next(),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "char", found: value })
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<String, ValueError> {
+        match value {
+            Value::String(x) => Ok(x),
+            found => Err(ValueError { expected: "String", found }),
+        }
+    }
+}
+
+// a map with a `shape` and `data` list works too, which is how a tensor is written in a program
+impl TryFrom<Value> for Tensor {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<Tensor, ValueError> {
+        let x = match &value {
+            Value::Tensor(x) => Some(x.clone()),
+            Value::Map(map) if map.len() == 2 => match (map.get("shape").cloned().map(Vec::<usize>::try_from), map.get("data").cloned().map(Vec::<f64>::try_from)) {
+                (Some(Ok(shape)), Some(Ok(data))) => Tensor::new(shape, data),
+                _ => None,
+            },
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "Tensor", found: value })
+    }
+}
+
+// every element has to convert, bytes count as a list of integers
+impl<T: TryFrom<Value>> TryFrom<Value> for Vec<T> {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<Vec<T>, ValueError> {
+        let x = match &value {
+            Value::List(items) => items.iter().cloned().map(T::try_from).collect::<Result<Vec<T>, _>>().ok(),
+            Value::Bytes(bytes) => bytes.iter().map(|b| T::try_from(Value::Int(i64::from(*b)))).collect::<Result<Vec<T>, _>>().ok(),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "list", found: value })
+    }
+}
+
+impl<T: TryFrom<Value>> TryFrom<Value> for BTreeMap<String, T> {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<BTreeMap<String, T>, ValueError> {
+        let x = match &value {
+            Value::Map(map) => map.iter().map(|(k, v)| T::try_from(v.clone()).map(|v| (k.clone(), v))).collect::<Result<BTreeMap<String, T>, _>>().ok(),
+            _ => None,
+        };
+        x.ok_or(ValueError { expected: "map", found: value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+    use crate::value::{ Opaque, Tensor, ToValue, Value, ViaFrom, ViaOpaque };
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Value::Int(3), Value::from(3_u8));
+        assert_eq!(Value::UInt(u64::MAX), Value::from(u64::MAX));
+        assert_eq!(Value::List(vec![Value::Int(1), Value::Int(2)]), Value::from(vec![1, 2]));
+        assert_eq!(Ok(200_u8), u8::try_from(Value::Int(200)));
+        assert_eq!(Ok(3_i32), i32::try_from(Value::Float(3.0)));
+        assert!(i8::try_from(Value::Int(200)).is_err());
+        assert!(i32::try_from(Value::Float(3.5)).is_err());
+        assert!(f64::try_from(Value::Int(i64::MAX)).is_err()); // would round
+        assert!(f32::try_from(Value::Float(1e300)).is_err());
+        assert_eq!(Ok(0.1_f32), f32::try_from(Value::Float(0.1)));
+        assert_eq!(Ok(vec![1_u8, 2]), Vec::<u8>::try_from(Value::Bytes(vec![1, 2])));
+
+        // lossy conversions have to be asked for
+        assert_eq!(Err(Value::Float(2.7)), Value::Float(2.7).cast::<i32>());
+        assert_eq!(Ok(2), Value::Float(2.7).cast_lossy::<i32>());
+        assert_eq!(Ok(255_u8), Value::Float(1e10).cast_lossy::<u8>());
+        assert_eq!(Ok(1.0_f32), Value::Bool(true).cast_lossy::<f32>());
+        assert_eq!(Ok("x".to_owned()), Value::from("x").cast_lossy::<String>());
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)] // the `&&` is what lets `ToValue` pick the conversion
+    fn any_type() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Point(i32, i32);
+
+        // every type makes it through `from_any` and back
+        assert_eq!(Value::Int(-4), Value::from_any(-4_i16));
+        assert_eq!(Value::from(vec![1_u8, 2]), Value::from_any(vec![1_u8, 2]));
+        assert_eq!(Ok(vec![1_u8, 2]), Value::from_any(vec![1_u8, 2]).cast::<Vec<u8>>());
+        assert_eq!(Ok('c'), Value::from_any('c').cast::<char>());
+        assert_eq!(Ok(()), Value::from_any(()).cast::<()>());
+
+        let point = Value::from_any(Point(1, 2));
+        assert!(point.kind().ends_with("Point"));
+        assert_eq!(point, point.clone());
+        assert_ne!(point, Value::from_any(Point(1, 2)));
+        assert_eq!(Ok(Point(1, 2)), point.clone().cast::<Point>());
+        assert_eq!(Err(point.clone()), point.cast::<i32>());
+        assert_eq!(Some(&5), Opaque::new(5).downcast_ref::<i32>());
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_owned(), 1.5);
+        assert_eq!(Ok(map.clone()), Value::from_any(map).cast::<BTreeMap<String, f64>>());
+
+        // picked from the type without looking at it at run time, same result as `from_any`
+        assert_eq!(Value::from_any(-4_i16), (&&ToValue::new(-4_i16)).to_value());
+        assert_eq!(Value::from_any(vec![1_u8, 2]), (&&ToValue::new(vec![1_u8, 2])).to_value());
+        assert_eq!(Value::Int(1), (&&ToValue::new(Value::Int(1))).to_value());
+        assert_eq!(Ok(Point(3, 4)), (&&ToValue::new(Point(3, 4))).to_value().cast::<Point>());
+        assert_eq!(Ok(vec![Point(5, 6)]), (&&ToValue::new(vec![Point(5, 6)])).to_value().cast::<Vec<Point>>());
+    }
+
+    #[test]
+    fn tensors() {
+        assert!(Tensor::new(vec![2, 2], vec![1.0; 3]).is_none());
+        let tensor = Tensor::new(vec![2, 3], vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]).unwrap();
+        assert_eq!(Some(5.0), tensor.get(&[1, 2]));
+        assert_eq!(None, tensor.get(&[2, 0]));
+
+        let mut map = BTreeMap::new();
+        map.insert("shape".to_owned(), Value::from(vec![2_usize, 3]));
+        map.insert("data".to_owned(), Value::from(tensor.data().to_vec()));
+        assert_eq!(Ok(tensor), Value::Map(map).cast::<Tensor>());
+    }
+}
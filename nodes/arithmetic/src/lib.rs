@@ -1,5 +1,9 @@
 use delta_lib_macro::{delta_node_struct, delta_node_impl, RegisterDeltaNode};
-use delta_lib::{DeltaNode, Impulse};
+use delta_lib::{delta_plugin, DeltaNode, Impulse, LogLevel};
+
+// every numeric operation for every integer and float type, e.g. `numeric::Subu8`
+pub mod numeric;
+pub use numeric::NumericNodes;
 
 #[delta_node_struct]
 #[derive(RegisterDeltaNode)]
@@ -13,8 +17,13 @@ pub struct Addi32 {
     // delta-noreset:   - Tells macros not to generate reset code for the field.
     //                  - Can be used to retain state throughout executions and only changes when new data comes in.
     //                      
-    // delta-default(...):  - Specify the default value of the field when reseting. 
+    // delta-default(...):  - Specify the default value of the field when initializing and reseting. 
     //                      - The data inside the `()` will be taken as gospel, so it must be 100% syntactically correct.
+    //                      - String literals are converted automatically for `String` fields.
+    //                      - Since the field always holds a usable value, it is an optional input: the node is ready without it.
+    //
+    // delta-output:    - Marks an `Impulse<T>` field as an extra output port, next to the `out` port that on_exec returns.
+    //                  - The node fills it while executing, it is taken (and reset to NOOP) when the outputs are collected.
     x: i32,
     y: i32,
 
@@ -40,13 +49,13 @@ pub struct Addi32 {
     pub my_controlled_public: i64, // this will generate set and reset functions but will not be included in the overall reset
 }
 
-// TODO: allow user to choose to append or prepend their custom post_execute to before or after the default reset if they don't want to fully override it
-#[delta_node_impl( on_exec = "custom_execute", post_exec = "custom_postexecute" )]
+// the custom post_execute runs before the default reset, "after_reset" would run it after and "replace" would skip the reset entirely
+#[delta_node_impl( on_exec = "custom_execute", post_exec = "custom_postexecute", post_exec_order = "before_reset" )]
 impl Addi32 {
     // these will be generated (by the struct macro )
 
-    // maybe to avoid the ugly double underscore we can add an attribute flag to the methods to signal the macro
-    // macro would then wrap the method in the standardized name 
+    // to avoid the ugly double underscore we can also put `#[delta(on_exec)]` on a method to signal the macro
+    // macro will then wrap the method in the standardized name 
     // if an attribute flag doesn't exist and the double underscore name doesn't exist then generate a default method
 
 
@@ -72,8 +81,8 @@ impl Addi32 {
 
     // the macro will recognize that __pre_execute is already implemented and use it
     fn __pre_execute(&mut self) {
-        // do nothing before execution
-        println!("Pre Execution!!!");
+        // only log that the node is about to execute
+        self.__log(LogLevel::Debug, "Pre Execution!!!");
     }
 
     // we can also name the functions whatever we want,
@@ -85,10 +94,17 @@ impl Addi32 {
     }
 
     fn custom_postexecute(&mut self) {
-        println!("I have added {} times so far!", self.my_ignored);
+        self.__log(LogLevel::Info, format!("I have added {} times so far!", self.my_ignored));
     }
 }
 
+// every node this crate exports when it is loaded as a plugin
+delta_plugin!(
+    Addi32,
+    NumericNodes<i8>, NumericNodes<i16>, NumericNodes<i32>, NumericNodes<i64>,
+    NumericNodes<u8>, NumericNodes<u16>, NumericNodes<u32>, NumericNodes<u64>,
+    NumericNodes<f32>, NumericNodes<f64>,
+);
 
 #[cfg(test)]
 mod tests {
@@ -103,4 +119,356 @@ mod tests {
         adder.__set_y(100);
         assert_eq!(Impulse::SEND(200), adder.__execute());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn state_survives_executions() {
+        let mut adder: Box<Addi32> = Addi32::__initialize();
+        for i in 0..3 {
+            adder.__set_x(i);
+            adder.__set_y(i);
+            assert_eq!(Impulse::SEND(2 * i), adder.__execute());
+        }
+
+        // `my_ignored` counts the executions, starting from its default of 10
+        assert_eq!(13, adder.my_ignored);
+    }
+
+    #[test]
+    fn readiness() {
+        let mut adder: Box<Addi32> = Addi32::__initialize();
+        assert_eq!(5, adder.__num_fields);
+        assert!(!adder.__is_ready());
+
+        // setting the same input twice only counts once
+        adder.__set_x(1);
+        adder.__set_x(2);
+        adder.__set_y(1);
+        adder.__set_my_generated_public(0.0);
+        assert!(!adder.__is_ready());
+        adder.__set_my_controlled_public(0);
+        assert!(adder.__is_ready());
+
+        // `custom_reset` has a default, so it is always set
+        assert!(adder.__is_set(2));
+        adder.__reset_custom_reset();
+        assert!(adder.__is_set(2));
+
+        // resetting an input twice does not affect any other input
+        adder.__reset_x();
+        adder.__reset_x();
+        assert!(!adder.__is_set(0));
+        assert!(adder.__is_set(1));
+        adder.__set_x(1);
+        assert!(adder.__is_ready());
+
+        // a full reset leaves the noreset inputs set
+        adder.__reset();
+        assert!(!adder.__is_ready());
+        assert!(!adder.__is_set(0));
+        assert!(adder.__is_set(4));
+
+        // executing resets too, even though post_exec is mapped
+        adder.__set_x(1);
+        adder.__set_y(1);
+        adder.__set_my_generated_public(0.0);
+        assert!(adder.__is_ready());
+        adder.__execute();
+        assert!(!adder.__is_ready());
+        assert!(adder.__is_set(4));
+    }
+
+    #[test]
+    fn delta_defaults() {
+        let mut adder: Box<Addi32> = Addi32::__initialize();
+        assert_eq!(10, adder.my_ignored);
+        assert_eq!("Hello World!", adder.custom_reset);
+
+        // resetting goes back to the delta_default value, not the Default of the type
+        adder.__set_custom_reset("Goodbye".to_owned());
+        adder.__reset_custom_reset();
+        assert_eq!("Hello World!", adder.custom_reset);
+    }
+
+    // nodes do not have to return `Impulse<i32>`, the DeltaNode type comes from the mapped on_exec
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Halvef64 {
+        x: f64,
+    }
+
+    #[delta_lib_macro::delta_node_impl( on_exec = "halve" )]
+    impl Halvef64 {
+        fn halve(&mut self) -> Impulse<f64> {
+            Impulse::SEND(self.x / 2.0)
+        }
+    }
+
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Greet {
+        name: String,
+    }
+
+    #[delta_lib_macro::delta_node_impl( on_exec = "greet" )]
+    impl Greet {
+        fn greet(&mut self) -> Impulse<String> {
+            Impulse::SEND(format!("Hello {}!", self.name))
+        }
+    }
+
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Repeatf32 {
+        x: f32,
+        n: usize,
+    }
+
+    // writing `__on_execute` directly works the same as mapping it
+    #[delta_lib_macro::delta_node_impl]
+    impl Repeatf32 {
+        fn __on_execute(&mut self) -> Impulse<Vec<f32>> {
+            Impulse::SEND(vec![self.x; self.n])
+        }
+    }
+
+    // a node with a second output port next to the on_execute return value
+    /// Integer division that also sends the remainder.
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct DivRemi32 {
+        /// The dividend
+        x: i32,
+        /// The divisor
+        y: i32,
+
+        /// What is left over
+        #[delta_output]
+        remainder: Impulse<i32>,
+    }
+
+    #[delta_lib_macro::delta_node_impl( on_exec = "divide" )]
+    impl DivRemi32 {
+        fn divide(&mut self) -> Impulse<i32> {
+            self.remainder = Impulse::SEND(self.x % self.y);
+            Impulse::SEND(self.x / self.y)
+        }
+    }
+
+    #[test]
+    fn output_ports() {
+        use delta_lib::{DeltaPorts, PortDescriptor, PortKind, PortError, Value};
+
+        assert_eq!(DivRemi32::__ports(), vec![
+            PortDescriptor { name: "x".into(), ty: "i32", index: 0, kind: PortKind::Input, required: true, dynamic: false, error: false },
+            PortDescriptor { name: "y".into(), ty: "i32", index: 1, kind: PortKind::Input, required: true, dynamic: false, error: false },
+            PortDescriptor { name: "out".into(), ty: "i32", index: 0, kind: PortKind::Output, required: false, dynamic: false, error: false },
+            PortDescriptor { name: "remainder".into(), ty: "i32", index: 1, kind: PortKind::Output, required: false, dynamic: false, error: false },
+        ]);
+
+        let mut div: Box<DivRemi32> = DivRemi32::__initialize();
+        assert_eq!(Ok(()), div.__set_port(0, &Value::Int(7)));
+        assert_eq!(Ok(()), div.__set_port(1, &Value::Float(2.0))); // whole numbers convert without losing anything
+        assert_eq!(Err(PortError::TypeMismatch { port: "y".to_owned(), expected: "i32", found: "float" }), div.__set_port(1, &Value::Float(2.5)));
+        assert_eq!(Err(PortError::UnknownPort(2)), div.__set_port(2, &Value::Int(2)));
+
+        assert_eq!(vec![Impulse::SEND(Value::Int(3)), Impulse::SEND(Value::Int(1))], div.__execute_ports());
+
+        // outputs are taken when they are collected
+        assert_eq!(Impulse::NOOP, div.remainder);
+        assert_eq!("Vec<f32>", Repeatf32::__RETURN_TYPE);
+    }
+
+    // hooks can also be picked with an attribute on the method instead of in the macro arguments
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Countu32 {
+        x: u32,
+
+        #[delta_ignore]
+        calls: Vec<&'static str>,
+    }
+
+    #[delta_lib_macro::delta_node_impl( init = "start" )]
+    impl Countu32 {
+        fn start(&mut self) {
+            self.calls.push("init");
+        }
+
+        #[delta(pre_exec, post_exec)]
+        fn around(&mut self) {
+            self.calls.push("around");
+        }
+
+        #[delta(on_exec)]
+        fn count(&mut self) -> Impulse<u32> {
+            self.calls.push("exec");
+            Impulse::SEND(self.x + 1)
+        }
+    }
+
+    #[test]
+    fn attribute_mappings() {
+        let mut count: Box<Countu32> = Countu32::__initialize();
+        count.__set_x(1);
+        assert_eq!(Impulse::SEND(2), count.__execute());
+        assert_eq!(vec!["init", "around", "exec", "around"], count.calls);
+        assert_eq!("u32", Countu32::__RETURN_TYPE);
+    }
+
+    // post_exec and init can be placed around the built-in reset and default initialization
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Orderedi32 {
+        #[delta_default(1)]
+        x: i32,
+
+        #[delta_ignore]
+        seen: Vec<i32>,
+    }
+
+    #[delta_lib_macro::delta_node_impl( post_exec = "remember", post_exec_order = "after_reset" )]
+    impl Orderedi32 {
+        #[delta(on_exec)]
+        fn run(&mut self) -> Impulse<i32> {
+            Impulse::SEND(self.x)
+        }
+
+        fn remember(&mut self) {
+            self.seen.push(self.x);
+        }
+    }
+
+    #[delta_lib_macro::delta_node_struct]
+    #[derive(delta_lib_macro::RegisterDeltaNode)]
+    struct Stickyi32 {
+        x: i32,
+    }
+
+    static STICKY_CREATED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    #[delta_lib_macro::delta_node_impl( init = "count", init_order = "before_default", post_exec_order = "replace" )]
+    impl Stickyi32 {
+        fn count() {
+            STICKY_CREATED.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn __on_execute(&mut self) -> Impulse<i32> {
+            Impulse::SEND(self.x)
+        }
+    }
+
+    #[test]
+    fn hook_orders() {
+        // after_reset sees the reset values
+        let mut ordered: Box<Orderedi32> = Orderedi32::__initialize();
+        ordered.__set_x(5);
+        assert_eq!(Impulse::SEND(5), ordered.__execute());
+        assert_eq!(vec![1], ordered.seen);
+
+        // replace keeps the inputs around between executions
+        let mut sticky: Box<Stickyi32> = Stickyi32::__initialize();
+        assert_eq!(1, STICKY_CREATED.load(std::sync::atomic::Ordering::SeqCst));
+        sticky.__set_x(3);
+        assert_eq!(Impulse::SEND(3), sticky.__execute());
+        assert!(sticky.__is_ready());
+        assert_eq!(Impulse::SEND(3), sticky.__execute());
+    }
+
+    #[test]
+    fn describe() {
+        use delta_lib::{ FieldSchema, ResetPolicy };
+
+        let schema = Addi32::__describe();
+        assert_eq!("arithmetic::Addi32", schema.name);
+        assert_eq!(vec!["x", "y", "custom_reset", "my_generated_public", "my_controlled_public"], schema.inputs.iter().map(|f| &*f.name).collect::<Vec<_>>());
+        assert_eq!(&FieldSchema {
+            name: "custom_reset".into(), ty: "String", default: Some("\"Hello World!\""), reset: ResetPolicy::NoReset, public: false, required: false, doc: "",
+        }, schema.input("custom_reset").unwrap());
+        assert_eq!(ResetPolicy::Reset, schema.input("my_generated_public").unwrap().reset);
+        assert!(schema.input("my_generated_public").unwrap().public);
+        assert_eq!(ResetPolicy::NoReset, schema.input("my_controlled_public").unwrap().reset);
+
+        // ignored fields (public ones are ignored unless they say otherwise) are only state, not inputs
+        assert_eq!(vec!["my_ignored", "my_default_public"], schema.state.iter().map(|f| &*f.name).collect::<Vec<_>>());
+        assert_eq!((Some("10"), ResetPolicy::Never, "# comments! (we don't want these yuck!)"),
+            (schema.state[0].default, schema.state[0].reset, schema.state[0].doc));
+
+        let schema = DivRemi32::__describe();
+        assert_eq!("Integer division that also sends the remainder.", schema.doc);
+        assert_eq!("The divisor", schema.input("y").unwrap().doc);
+        assert!(schema.input("y").unwrap().required);
+        assert_eq!(vec![("out", "i32", ""), ("remainder", "i32", "What is left over")],
+            schema.outputs.iter().map(|f| (&*f.name, f.ty, f.doc)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = delta_lib::NodeRegistry::new();
+        crate::__delta_plugin_register(&mut registry);
+        Halvef64::__register(&mut registry);
+        assert_eq!(delta_lib::PLUGIN_ABI_VERSION, crate::__delta_plugin_abi_version());
+
+        // the numeric pack is checked in numeric.rs
+        let names: Vec<&str> = registry.nodes().map(|n| n.name.as_str()).filter(|n| !n.starts_with("arithmetic::numeric::")).collect();
+        assert_eq!(vec!["arithmetic::Addi32", "arithmetic::tests::Halvef64"], names);
+        assert_eq!(<Addi32 as delta_lib::DeltaPorts>::__ports(), registry.get("arithmetic::Addi32").unwrap().ports);
+
+        // every node created is a fresh, initialized one
+        let node = registry.create(Addi32::__TYPE_NAME).unwrap();
+        assert_eq!(Some(10), node.__as_any().downcast_ref::<Addi32>().map(|a| a.my_ignored));
+        assert_eq!(Err(delta_lib::RegistryError::UnknownNode("Addi32".to_owned())), registry.create("Addi32").map(|_| ()));
+    }
+
+    #[test]
+    fn generic_return_types() {
+        let mut halve: Box<Halvef64> = Halvef64::__initialize();
+        halve.__set_x(5.0);
+        assert_eq!(Impulse::SEND(2.5), halve.__execute());
+
+        let mut greet: Box<Greet> = Greet::__initialize();
+        greet.__set_name("delta".to_owned());
+        assert_eq!(Impulse::SEND("Hello delta!".to_owned()), greet.__execute());
+
+        let mut repeat: Box<Repeatf32> = Repeatf32::__initialize();
+        repeat.__set_x(1.5);
+        repeat.__set_n(3);
+        assert_eq!(Impulse::SEND(vec![1.5, 1.5, 1.5]), repeat.__execute());
+    }
+
+    #[test]
+    fn set_by_name() {
+        use delta_lib::{SetError, Value};
+
+        let mut adder: Box<Addi32> = Addi32::__initialize();
+        adder.__set_by_name("x", Value::Int(2)).unwrap();
+        adder.__set_by_name("y", Value::UInt(3)).unwrap();
+        adder.__set_by_name("custom_reset", Value::String("set".to_owned())).unwrap();
+        adder.__set_by_name("my_generated_public", Value::Int(1)).unwrap();
+        assert_eq!(("set", 1.0), (adder.custom_reset.as_str(), adder.my_generated_public));
+        assert_eq!(Impulse::SEND(5), adder.__execute());
+
+        assert_eq!(Err(SetError::UnknownField("z".to_owned())), adder.__set_by_name("z", Value::Int(1)));
+        assert_eq!(Err(SetError::NotAnInput("my_ignored".to_owned())), adder.__set_by_name("my_ignored", Value::Int(1)));
+        assert_eq!(Err(SetError::NotAnInput("my_default_public".to_owned())), adder.__set_by_name("my_default_public", Value::Float(1.0)));
+        assert_eq!(Err(SetError::TypeMismatch { field: "x".to_owned(), expected: "i32", found: Value::Float(1.5) }), adder.__set_by_name("x", Value::Float(1.5)));
+        assert_eq!(Err(SetError::TypeMismatch { field: "x".to_owned(), expected: "i32", found: Value::Int(1 << 40) }), adder.__set_by_name("x", Value::Int(1 << 40)));
+        assert!(!adder.__is_ready()); // a failed set does not count
+
+        let mut divrem: Box<DivRemi32> = DivRemi32::__initialize();
+        assert_eq!(Err(SetError::NotAnInput("remainder".to_owned())), divrem.__set_by_name("remainder", Value::Int(1)));
+
+        // lists convert element by element
+        let mut repeat = Repeatf32::__initialize();
+        repeat.__set_by_name("x", Value::List(vec![])).unwrap_err();
+        repeat.__set_by_name("x", Value::Int(2)).unwrap();
+        repeat.__set_by_name("n", Value::Int(2)).unwrap();
+        assert_eq!(Impulse::SEND(vec![2.0, 2.0]), repeat.__execute());
+        assert_eq!(Ok(vec![1.0_f32, 2.5]), Value::List(vec![Value::Int(1), Value::Float(2.5)]).cast::<Vec<f32>>());
+        assert_eq!(Ok('d'), Value::String("d".to_owned()).cast::<char>());
+
+        // the same through a type-erased node
+        let mut node: Box<dyn delta_lib::DynDeltaNode> = Halvef64::__initialize();
+        node.__set_by_name_dyn("x", Value::Int(3)).unwrap();
+        assert!(node.__ready());
+    }
+}
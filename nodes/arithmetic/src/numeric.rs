@@ -0,0 +1,616 @@
+// the macros behind the numeric node pack, every node type is named after its operation and number type, e.g. `Subu8`.
+// addition is `Sum` rather than `Add`, `arithmetic::Addi32` is the crate's example node.
+// integer nodes that can overflow have an `overflow` input (checked by default), see `delta_lib::Overflow`.
+// division by zero, overflow and inputs outside of what an operation accepts are sent as `Impulse::ERROR`
+use std::marker::PhantomData;
+use delta_lib::{ DeltaError, DeltaNode, ErrorKind, Impulse };
+
+// registers every numeric node for the number type `T`, e.g. `NumericNodes::<u8>::__register(&mut registry)`
+pub struct NumericNodes<T>(PhantomData<T>);
+
+pub(crate) fn overflow_error(what: String, ty: &str) -> DeltaError {
+    DeltaError::new(ErrorKind::Overflow, format!("{} overflows {}", what, ty))
+}
+
+pub(crate) fn invalid_input(what: String) -> DeltaError {
+    DeltaError::new(ErrorKind::InvalidInput, what)
+}
+
+// `$x.checked_$op($y)` and friends, picked by the overflow mode
+macro_rules! overflowing {
+    ($mode:expr, $t:ident, $what:expr, $x:ident . $op:ident ( $($y:expr)? )) => {
+        paste::paste! {
+            match $mode {
+                ::delta_lib::Overflow::Checked => $x.[<checked_ $op>]($($y)?).ok_or_else(|| $crate::numeric::overflow_error($what, stringify!($t))),
+                ::delta_lib::Overflow::Wrapping => Ok($x.[<wrapping_ $op>]($($y)?)),
+                ::delta_lib::Overflow::Saturating => Ok($x.[<saturating_ $op>]($($y)?)),
+            }
+        }
+    };
+}
+
+// `x <op> y` on integers, in the node's overflow mode
+macro_rules! int_binary_node {
+    ($name:ident, $t:ident, $op:ident, $symbol:tt, $doc:tt) => {
+        paste::paste! {
+            #[doc = $doc]
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<$name $t>] {
+                x: $t,
+                y: $t,
+
+                #[delta_noreset]
+                #[delta_default(::delta_lib::Overflow::Checked)]
+                overflow: ::delta_lib::Overflow,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<$name $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    let (x, y) = (self.x, self.y);
+                    overflowing!(self.overflow, $t, format!("{} {} {}", x, stringify!($symbol), y), x.$op(y)).map(Impulse::SEND)
+                }
+            }
+        }
+    };
+}
+
+// `op(x)` on signed integers, in the node's overflow mode
+macro_rules! int_unary_node {
+    ($name:ident, $t:ident, $op:ident, $doc:tt) => {
+        paste::paste! {
+            #[doc = $doc]
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<$name $t>] {
+                x: $t,
+
+                #[delta_noreset]
+                #[delta_default(::delta_lib::Overflow::Checked)]
+                overflow: ::delta_lib::Overflow,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<$name $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    let x = self.x;
+                    overflowing!(self.overflow, $t, format!("{}({})", stringify!($op), x), x.$op()).map(Impulse::SEND)
+                }
+            }
+        }
+    };
+}
+
+// `x <op> y` that can not fail, on floats
+macro_rules! float_binary_node {
+    ($name:ident, $t:ident, $symbol:tt, $doc:tt) => {
+        paste::paste! {
+            #[doc = $doc]
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<$name $t>] {
+                x: $t,
+                y: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<$name $t>] {
+                fn run(&mut self) -> Impulse<$t> {
+                    Impulse::SEND(self.x $symbol self.y)
+                }
+            }
+        }
+    };
+}
+
+// nodes that work the same way on every number type
+macro_rules! common_nodes {
+    ($t:ident, $zero:tt) => {
+        paste::paste! {
+            /// The smaller of x and y.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Min $t>] {
+                x: $t,
+                y: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Min $t>] {
+                fn run(&mut self) -> Impulse<$t> {
+                    Impulse::SEND(self.x.min(self.y))
+                }
+            }
+
+            /// The larger of x and y.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Max $t>] {
+                x: $t,
+                y: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Max $t>] {
+                fn run(&mut self) -> Impulse<$t> {
+                    Impulse::SEND(self.x.max(self.y))
+                }
+            }
+
+            /// x limited to the range min..=max, min being larger than max is an error.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Clamp $t>] {
+                x: $t,
+                min: $t,
+                max: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Clamp $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    match self.min.partial_cmp(&self.max) {
+                        Some(::std::cmp::Ordering::Less) | Some(::std::cmp::Ordering::Equal) => Ok(Impulse::SEND(self.x.clamp(self.min, self.max))),
+                        _ => Err($crate::numeric::invalid_input(format!("can not clamp to {}..={}", self.min, self.max))),
+                    }
+                }
+            }
+
+            /// Sends its value every time it runs, set it once with the `value` input.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Const $t>] {
+                #[delta_noreset]
+                #[delta_default($zero)]
+                value: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Const $t>] {
+                fn run(&mut self) -> Impulse<$t> {
+                    Impulse::SEND(self.value)
+                }
+            }
+
+            compare_node!(Eq, $t, ==, "Whether x is equal to y.");
+            compare_node!(Ne, $t, !=, "Whether x is not equal to y.");
+            compare_node!(Lt, $t, <, "Whether x is less than y.");
+            compare_node!(Le, $t, <=, "Whether x is less than or equal to y.");
+            compare_node!(Gt, $t, >, "Whether x is greater than y.");
+            compare_node!(Ge, $t, >=, "Whether x is greater than or equal to y.");
+        }
+    };
+}
+
+macro_rules! compare_node {
+    ($name:ident, $t:ident, $symbol:tt, $doc:tt) => {
+        paste::paste! {
+            #[doc = $doc]
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<$name $t>] {
+                x: $t,
+                y: $t,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<$name $t>] {
+                fn run(&mut self) -> Impulse<bool> {
+                    Impulse::SEND(self.x $symbol self.y)
+                }
+            }
+        }
+    };
+}
+
+// every integer node except `Neg`, `Abs` and `Sqrt`, which depend on the integer being signed or not
+macro_rules! int_nodes {
+    ($t:ident) => {
+        int_binary_node!(Sum, $t, add, +, "Adds x and y.");
+        int_binary_node!(Sub, $t, sub, -, "Subtracts y from x.");
+        int_binary_node!(Mul, $t, mul, *, "Multiplies x by y.");
+        common_nodes!($t, 0);
+
+        paste::paste! {
+            /// Division, a divisor of zero is an error.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Div $t>] {
+                x: $t,
+                y: $t,
+
+                #[delta_noreset]
+                #[delta_default(::delta_lib::Overflow::Checked)]
+                overflow: ::delta_lib::Overflow,
+            }
+
+            /// The remainder of dividing x by y, a divisor of zero is an error.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Rem $t>] {
+                x: $t,
+                y: $t,
+
+                #[delta_noreset]
+                #[delta_default(::delta_lib::Overflow::Checked)]
+                overflow: ::delta_lib::Overflow,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Div $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    let (x, y) = (self.x, self.y);
+                    if y == 0 {
+                        return Err(::delta_lib::DeltaError::new(::delta_lib::ErrorKind::DivisionByZero, format!("{} / 0", x)));
+                    }
+                    overflowing!(self.overflow, $t, format!("{} / {}", x, y), x.div(y)).map(Impulse::SEND)
+                }
+            }
+
+            // the only remainder that overflows is `MIN % -1`, which is 0 when it wraps or saturates
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Rem $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    let (x, y) = (self.x, self.y);
+                    if y == 0 {
+                        return Err(::delta_lib::DeltaError::new(::delta_lib::ErrorKind::DivisionByZero, format!("{} % 0", x)));
+                    }
+                    match self.overflow {
+                        ::delta_lib::Overflow::Checked => x.checked_rem(y).ok_or_else(|| $crate::numeric::overflow_error(format!("{} % {}", x, y), stringify!($t))),
+                        _ => Ok(x.wrapping_rem(y)),
+                    }.map(Impulse::SEND)
+                }
+            }
+
+            /// x to the power of exp.
+            #[delta_lib_macro::delta_node_struct]
+            #[derive(delta_lib_macro::RegisterDeltaNode)]
+            pub struct [<Pow $t>] {
+                x: $t,
+                exp: u32,
+
+                #[delta_noreset]
+                #[delta_default(::delta_lib::Overflow::Checked)]
+                overflow: ::delta_lib::Overflow,
+            }
+
+            #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+            impl [<Pow $t>] {
+                fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                    let (x, exp) = (self.x, self.exp);
+                    overflowing!(self.overflow, $t, format!("{} ^ {}", x, exp), x.pow(exp)).map(Impulse::SEND)
+                }
+            }
+        }
+    };
+}
+
+macro_rules! signed_nodes {
+    ($($t:ident),*) => {
+        $(
+            int_nodes!($t);
+            int_unary_node!(Neg, $t, neg, "Negates x.");
+            int_unary_node!(Abs, $t, abs, "The absolute value of x.");
+
+            paste::paste! {
+                /// The integer square root of x, rounded down. Negative numbers are an error.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Sqrt $t>] {
+                    x: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Sqrt $t>] {
+                    fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                        match self.x.checked_isqrt() {
+                            Some(x) => Ok(Impulse::SEND(x)),
+                            None => Err($crate::numeric::invalid_input(format!("can not take the square root of {}", self.x))),
+                        }
+                    }
+                }
+
+                impl $crate::numeric::NumericNodes<$t> {
+                    pub fn __register(registry: &mut ::delta_lib::NodeRegistry) {
+                        register!(registry, $t, Sum, Sub, Mul, Div, Rem, Neg, Abs, Pow, Sqrt, Min, Max, Clamp, Const, Eq, Ne, Lt, Le, Gt, Ge);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! unsigned_nodes {
+    ($($t:ident),*) => {
+        $(
+            int_nodes!($t);
+
+            paste::paste! {
+                /// The integer square root of x, rounded down.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Sqrt $t>] {
+                    x: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Sqrt $t>] {
+                    fn run(&mut self) -> Impulse<$t> {
+                        Impulse::SEND(self.x.isqrt())
+                    }
+                }
+
+                impl $crate::numeric::NumericNodes<$t> {
+                    pub fn __register(registry: &mut ::delta_lib::NodeRegistry) {
+                        register!(registry, $t, Sum, Sub, Mul, Div, Rem, Pow, Sqrt, Min, Max, Clamp, Const, Eq, Ne, Lt, Le, Gt, Ge);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+// floats do not overflow (they become infinite), but dividing by zero and the square root of a negative number are still errors
+macro_rules! float_nodes {
+    ($($t:ident),*) => {
+        $(
+            float_binary_node!(Sum, $t, +, "Adds x and y.");
+            float_binary_node!(Sub, $t, -, "Subtracts y from x.");
+            float_binary_node!(Mul, $t, *, "Multiplies x by y.");
+            common_nodes!($t, 0.0);
+
+            paste::paste! {
+                /// Division, a divisor of zero is an error.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Div $t>] {
+                    x: $t,
+                    y: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Div $t>] {
+                    fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                        if self.y == 0.0 {
+                            return Err(::delta_lib::DeltaError::new(::delta_lib::ErrorKind::DivisionByZero, format!("{} / 0", self.x)));
+                        }
+                        Ok(Impulse::SEND(self.x / self.y))
+                    }
+                }
+
+                /// The remainder of dividing x by y, a divisor of zero is an error.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Rem $t>] {
+                    x: $t,
+                    y: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Rem $t>] {
+                    fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                        if self.y == 0.0 {
+                            return Err(::delta_lib::DeltaError::new(::delta_lib::ErrorKind::DivisionByZero, format!("{} % 0", self.x)));
+                        }
+                        Ok(Impulse::SEND(self.x % self.y))
+                    }
+                }
+
+                /// Negates x.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Neg $t>] {
+                    x: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Neg $t>] {
+                    fn run(&mut self) -> Impulse<$t> {
+                        Impulse::SEND(-self.x)
+                    }
+                }
+
+                /// The absolute value of x.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Abs $t>] {
+                    x: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Abs $t>] {
+                    fn run(&mut self) -> Impulse<$t> {
+                        Impulse::SEND(self.x.abs())
+                    }
+                }
+
+                /// x to the power of exp.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Pow $t>] {
+                    x: $t,
+                    exp: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Pow $t>] {
+                    fn run(&mut self) -> Impulse<$t> {
+                        Impulse::SEND(self.x.powf(self.exp))
+                    }
+                }
+
+                /// The square root of x. Negative numbers are an error.
+                #[delta_lib_macro::delta_node_struct]
+                #[derive(delta_lib_macro::RegisterDeltaNode)]
+                pub struct [<Sqrt $t>] {
+                    x: $t,
+                }
+
+                #[delta_lib_macro::delta_node_impl( on_exec = "run" )]
+                impl [<Sqrt $t>] {
+                    fn run(&mut self) -> Result<Impulse<$t>, ::delta_lib::DeltaError> {
+                        if self.x < 0.0 {
+                            return Err($crate::numeric::invalid_input(format!("can not take the square root of {}", self.x)));
+                        }
+                        Ok(Impulse::SEND(self.x.sqrt()))
+                    }
+                }
+
+                impl $crate::numeric::NumericNodes<$t> {
+                    pub fn __register(registry: &mut ::delta_lib::NodeRegistry) {
+                        register!(registry, $t, Sum, Sub, Mul, Div, Rem, Neg, Abs, Pow, Sqrt, Min, Max, Clamp, Const, Eq, Ne, Lt, Le, Gt, Ge);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! register {
+    ($registry:ident, $t:ident, $($name:ident),*) => {
+        paste::paste! {
+            $( [<$name $t>]::__register($registry); )*
+        }
+    };
+}
+
+signed_nodes!(i8, i16, i32, i64);
+unsigned_nodes!(u8, u16, u32, u64);
+float_nodes!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use delta_lib::{ ErrorKind, NodeRegistry, Overflow };
+    use crate::numeric::*;
+
+    fn error<T>(impulse: Impulse<T>) -> ErrorKind {
+        match impulse {
+            Impulse::ERROR(e) => e.kind,
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn overflow_modes() {
+        // checked by default, the mode sticks between executions
+        let mut sum = Sumi8::__initialize();
+        sum.__set_x(100);
+        sum.__set_y(100);
+        assert_eq!(ErrorKind::Overflow, error(sum.__execute()));
+        sum.__set_overflow(Overflow::Wrapping);
+        sum.__set_x(100);
+        sum.__set_y(100);
+        assert_eq!(Impulse::SEND(-56), sum.__execute());
+        sum.__set_x(100);
+        sum.__set_y(100);
+        sum.__set_by_name("overflow", delta_lib::Value::String("saturating".to_owned())).unwrap();
+        assert_eq!(Impulse::SEND(127), sum.__execute());
+
+        let mut sum = Sumi32::__initialize();
+        sum.__set_x(i32::MAX);
+        sum.__set_y(1);
+        assert_eq!(ErrorKind::Overflow, error(sum.__execute()));
+
+        let mut sub = Subu8::__initialize();
+        sub.__set_x(1);
+        sub.__set_y(2);
+        assert_eq!(ErrorKind::Overflow, error(sub.__execute()));
+
+        let mut neg = Negi16::__initialize();
+        neg.__set_x(i16::MIN);
+        assert_eq!(ErrorKind::Overflow, error(neg.__execute()));
+    }
+
+    #[test]
+    fn division() {
+        let mut div = Divi64::__initialize();
+        div.__set_x(1);
+        div.__set_y(0);
+        assert_eq!(ErrorKind::DivisionByZero, error(div.__execute()));
+        div.__set_x(i64::MIN);
+        div.__set_y(-1);
+        assert_eq!(ErrorKind::Overflow, error(div.__execute()));
+
+        let mut rem = Remf64::__initialize();
+        rem.__set_x(7.5);
+        rem.__set_y(0.0);
+        assert_eq!(ErrorKind::DivisionByZero, error(rem.__execute()));
+    }
+
+    #[test]
+    fn powers_and_roots() {
+        let mut pow = Powu32::__initialize();
+        pow.__set_x(2);
+        pow.__set_exp(10);
+        assert_eq!(Impulse::SEND(1024), pow.__execute());
+
+        let mut sqrt = Sqrti32::__initialize();
+        sqrt.__set_x(-4);
+        assert_eq!(ErrorKind::InvalidInput, error(sqrt.__execute()));
+        sqrt.__set_x(17);
+        assert_eq!(Impulse::SEND(4), sqrt.__execute());
+
+        let mut sqrt = Sqrtf32::__initialize();
+        sqrt.__set_x(-1.0);
+        assert_eq!(ErrorKind::InvalidInput, error(sqrt.__execute()));
+    }
+
+    #[test]
+    fn clamp_and_constants() {
+        let mut clamp = Clampf64::__initialize();
+        clamp.__set_x(5.0);
+        clamp.__set_min(0.0);
+        clamp.__set_max(1.0);
+        assert_eq!(Impulse::SEND(1.0), clamp.__execute());
+        clamp.__set_x(5.0);
+        clamp.__set_min(1.0);
+        clamp.__set_max(0.0);
+        assert_eq!(ErrorKind::InvalidInput, error(clamp.__execute()));
+        clamp.__set_x(5.0);
+        clamp.__set_min(f64::NAN);
+        clamp.__set_max(1.0);
+        assert_eq!(ErrorKind::InvalidInput, error(clamp.__execute()));
+
+        // constants are ready straight away and keep their value
+        let mut constant = Constu16::__initialize();
+        assert!(constant.__is_ready());
+        assert_eq!(Impulse::SEND(0), constant.__execute());
+        constant.__set_value(7);
+        assert_eq!(Impulse::SEND(7), constant.__execute());
+        assert_eq!(Impulse::SEND(7), constant.__execute());
+    }
+
+    #[test]
+    fn comparisons() {
+        let mut lt = Ltf32::__initialize();
+        lt.__set_x(1.0);
+        lt.__set_y(2.0);
+        assert_eq!(Impulse::SEND(true), lt.__execute());
+        let mut ne = Neu64::__initialize();
+        ne.__set_x(3);
+        ne.__set_y(3);
+        assert_eq!(Impulse::SEND(false), ne.__execute());
+    }
+
+    #[test]
+    fn registry() {
+        assert_eq!("arithmetic::numeric::Subu8", Subu8::__TYPE_NAME);
+
+        // unsigned integers have no Neg or Abs
+        let mut registry = NodeRegistry::new();
+        NumericNodes::<u8>::__register(&mut registry);
+        let names: Vec<&str> = registry.nodes().map(|n| n.name.trim_start_matches("arithmetic::numeric::")).collect();
+        assert_eq!(vec!["Clampu8", "Constu8", "Divu8", "Equ8", "Geu8", "Gtu8", "Leu8", "Ltu8", "Maxu8", "Minu8", "Mulu8", "Neu8",
+            "Powu8", "Remu8", "Sqrtu8", "Subu8", "Sumu8"], names);
+
+        // the short names do not clash with the crate's own `Addi32`
+        let mut registry = NodeRegistry::new();
+        NumericNodes::<i32>::__register(&mut registry);
+        crate::Addi32::__register(&mut registry);
+        assert_eq!(20, registry.len());
+        assert!(registry.contains("arithmetic::numeric::Sumi32"));
+        assert!(registry.contains("arithmetic::numeric::Absi32"));
+        assert_eq!(1, registry.nodes().filter(|n| n.name.ends_with("::Addi32")).count());
+    }
+}
@@ -0,0 +1,336 @@
+use std::cmp::Ordering;
+use delta_lib_macro::{delta_node_struct, delta_node_impl, RegisterDeltaNode};
+use delta_lib::{delta_plugin, DeltaError, DeltaNode, ErrorKind, Impulse, Value};
+
+// the control flow nodes route a value to one of their outputs and send NOOP on every other one,
+// so only the nodes downstream of the branch that was taken run
+
+/// Whether a and b are both true.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct And {
+    a: bool,
+    b: bool,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl And {
+    fn run(&mut self) -> Impulse<bool> {
+        Impulse::SEND(self.a && self.b)
+    }
+}
+
+/// Whether a or b (or both) are true.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Or {
+    a: bool,
+    b: bool,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Or {
+    fn run(&mut self) -> Impulse<bool> {
+        Impulse::SEND(self.a || self.b)
+    }
+}
+
+/// Whether exactly one of a and b is true.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Xor {
+    a: bool,
+    b: bool,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Xor {
+    fn run(&mut self) -> Impulse<bool> {
+        Impulse::SEND(self.a ^ self.b)
+    }
+}
+
+/// The opposite of x.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Not {
+    x: bool,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Not {
+    fn run(&mut self) -> Impulse<bool> {
+        Impulse::SEND(!self.x)
+    }
+}
+
+// numbers compare by value whatever their variant, everything else only compares to the same kind of value
+fn compare(x: &Value, y: &Value) -> Option<Ordering> {
+    match (x, y) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::UInt(x), Value::UInt(y)) => Some(x.cmp(y)),
+        (Value::Int(x), Value::UInt(y)) => Some(i128::from(*x).cmp(&i128::from(*y))),
+        (Value::UInt(x), Value::Int(y)) => Some(i128::from(*x).cmp(&i128::from(*y))),
+        (Value::Float(x), Value::Float(y)) => x.partial_cmp(y),
+        (Value::Float(x), Value::Int(y)) => x.partial_cmp(&(*y as f64)),
+        (Value::Float(x), Value::UInt(y)) => x.partial_cmp(&(*y as f64)),
+        (Value::Int(x), Value::Float(y)) => (*x as f64).partial_cmp(y),
+        (Value::UInt(x), Value::Float(y)) => (*x as f64).partial_cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (x, y) if x == y => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn equal(x: &Value, y: &Value) -> bool {
+    compare(x, y) == Some(Ordering::Equal)
+}
+
+/// Compares x to y with `op`, one of `==`, `!=`, `<`, `<=`, `>` or `>=`. Numbers of any type can be compared,
+/// other values only to values of the same kind. Ordering values that can not be ordered is an error.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Compare {
+    x: Value,
+    y: Value,
+
+    #[delta_noreset]
+    #[delta_default("==")]
+    op: String,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Compare {
+    fn run(&mut self) -> Result<Impulse<bool>, DeltaError> {
+        let ordering = compare(&self.x, &self.y);
+        let ordered = |accept: fn(Ordering) -> bool| ordering.map(accept).ok_or_else(|| DeltaError::new(ErrorKind::InvalidInput,
+            format!("can not order {} and {}", self.x.kind(), self.y.kind())));
+
+        Ok(Impulse::SEND(match self.op.as_str() {
+            "==" => ordering == Some(Ordering::Equal),
+            "!=" => ordering != Some(Ordering::Equal),
+            "<" => ordered(|o| o == Ordering::Less)?,
+            "<=" => ordered(|o| o != Ordering::Greater)?,
+            ">" => ordered(|o| o == Ordering::Greater)?,
+            ">=" => ordered(|o| o != Ordering::Less)?,
+            op => return Err(DeltaError::new(ErrorKind::InvalidInput, format!("unknown comparison `{}`", op))),
+        }))
+    }
+}
+
+/// Sends the value on `out` if the condition is true, otherwise on `otherwise`.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct If {
+    condition: bool,
+    value: Value,
+
+    /// The value, when the condition is false
+    #[delta_output]
+    otherwise: Impulse<Value>,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl If {
+    fn run(&mut self) -> Impulse<Value> {
+        let value = std::mem::take(&mut self.value);
+        if self.condition {
+            Impulse::SEND(value)
+        } else {
+            self.otherwise = Impulse::SEND(value);
+            Impulse::NOOP
+        }
+    }
+}
+
+/// Forwards the value while the gate is enabled. The gate starts out closed and stays the way it was last set.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Gate {
+    value: Value,
+
+    #[delta_noreset]
+    #[delta_default(false)]
+    enabled: bool,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Gate {
+    fn run(&mut self) -> Impulse<Value> {
+        if self.enabled { Impulse::SEND(std::mem::take(&mut self.value)) } else { Impulse::NOOP }
+    }
+}
+
+/// Forwards whichever input it got, a if both are set. Used to join the branches of an `If` or `Switch` back together.
+// both inputs are optional so the merge runs when only one branch sent something,
+// which means a unit value can not be merged: it looks the same as no value
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Merge {
+    #[delta_default(Value::Unit)]
+    a: Value,
+    #[delta_default(Value::Unit)]
+    b: Value,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Merge {
+    fn run(&mut self) -> Impulse<Value> {
+        match (std::mem::take(&mut self.a), std::mem::take(&mut self.b)) {
+            (Value::Unit, Value::Unit) => Impulse::NOOP,
+            (Value::Unit, value) | (value, _) => Impulse::SEND(value),
+        }
+    }
+}
+
+/// Sends the value on the output of the first case equal to the key, or on `out` if there is none.
+/// Keys compare like `Compare` does, so `1` matches `1.0`. There are outputs for up to four cases.
+#[delta_node_struct]
+#[derive(RegisterDeltaNode)]
+pub struct Switch {
+    key: Value,
+    value: Value,
+
+    /// The key each output is taken for, in order
+    #[delta_noreset]
+    #[delta_default(Vec::new())]
+    cases: Vec<Value>,
+
+    #[delta_output]
+    case0: Impulse<Value>,
+    #[delta_output]
+    case1: Impulse<Value>,
+    #[delta_output]
+    case2: Impulse<Value>,
+    #[delta_output]
+    case3: Impulse<Value>,
+}
+
+#[delta_node_impl( on_exec = "run" )]
+impl Switch {
+    fn run(&mut self) -> Result<Impulse<Value>, DeltaError> {
+        let value = std::mem::take(&mut self.value);
+        let case = self.cases.iter().position(|case| equal(case, &self.key));
+        let outputs = [&mut self.case0, &mut self.case1, &mut self.case2, &mut self.case3];
+        if self.cases.len() > outputs.len() {
+            return Err(DeltaError::new(ErrorKind::InvalidInput, format!("a switch has at most {} cases, got {}", outputs.len(), self.cases.len())));
+        }
+
+        match case {
+            Some(i) => {
+                *outputs[i] = Impulse::SEND(value);
+                Ok(Impulse::NOOP)
+            },
+            None => Ok(Impulse::SEND(value)),
+        }
+    }
+}
+
+// every node this crate exports when it is loaded as a plugin
+delta_plugin!(And, Or, Xor, Not, Compare, If, Gate, Merge, Switch);
+
+#[cfg(test)]
+mod tests {
+    use delta_lib::{DeltaPorts, ErrorKind, Impulse, Value};
+    use crate::*;
+
+    #[test]
+    fn boolean() {
+        for &(a, b) in &[(false, false), (false, true), (true, false), (true, true)] {
+            let mut and = And::__initialize();
+            and.__set_a(a);
+            and.__set_b(b);
+            assert_eq!(Impulse::SEND(a && b), and.__execute());
+
+            let mut or = Or::__initialize();
+            or.__set_a(a);
+            or.__set_b(b);
+            assert_eq!(Impulse::SEND(a || b), or.__execute());
+
+            let mut xor = Xor::__initialize();
+            xor.__set_a(a);
+            xor.__set_b(b);
+            assert_eq!(Impulse::SEND(a != b), xor.__execute());
+        }
+
+        let mut not = Not::__initialize();
+        not.__set_x(true);
+        assert_eq!(Impulse::SEND(false), not.__execute());
+    }
+
+    #[test]
+    fn comparator() {
+        let mut cmp = Compare::__initialize();
+        let mut check = |x: Value, op: &str, y: Value| {
+            cmp.__set_x(x);
+            cmp.__set_y(y);
+            cmp.__set_op(op.to_owned());
+            cmp.__execute()
+        };
+
+        assert_eq!(Impulse::SEND(true), check(Value::Int(1), "==", Value::Float(1.0)));
+        assert_eq!(Impulse::SEND(true), check(Value::Int(-1), "<", Value::UInt(u64::MAX)));
+        assert_eq!(Impulse::SEND(false), check(Value::Float(f64::NAN), "==", Value::Float(f64::NAN)));
+        assert_eq!(Impulse::SEND(true), check(Value::String("a".to_owned()), "<=", Value::String("b".to_owned())));
+        assert_eq!(Impulse::SEND(true), check(Value::String("1".to_owned()), "!=", Value::Int(1)));
+        assert!(matches!(check(Value::String("1".to_owned()), ">", Value::Int(1)), Impulse::ERROR(e) if e.kind == ErrorKind::InvalidInput));
+        assert!(matches!(check(Value::Int(1), "=", Value::Int(1)), Impulse::ERROR(e) if e.kind == ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn branches() {
+        let mut branch = If::__initialize();
+        branch.__set_condition(true);
+        branch.__set_value(Value::Int(1));
+        assert_eq!(vec![Impulse::SEND(Value::Int(1)), Impulse::NOOP], branch.__execute_ports());
+        branch.__set_condition(false);
+        branch.__set_value(Value::Int(2));
+        assert_eq!(vec![Impulse::NOOP, Impulse::SEND(Value::Int(2))], branch.__execute_ports());
+
+        // the gate is closed until it is enabled, and stays open afterwards
+        let mut gate = Gate::__initialize();
+        assert!(!gate.__is_ready());
+        gate.__set_value(Value::Int(1));
+        assert_eq!(Impulse::NOOP, gate.__execute());
+        gate.__set_enabled(true);
+        gate.__set_value(Value::Int(2));
+        assert_eq!(Impulse::SEND(Value::Int(2)), gate.__execute());
+        gate.__set_value(Value::Int(3));
+        assert_eq!(Impulse::SEND(Value::Int(3)), gate.__execute());
+
+        let mut switch = Switch::__initialize();
+        switch.__set_cases(vec![Value::String("a".to_owned()), Value::Int(2)]);
+        switch.__set_key(Value::Float(2.0));
+        switch.__set_value(Value::Bool(true));
+        assert_eq!(vec![Impulse::NOOP, Impulse::NOOP, Impulse::SEND(Value::Bool(true)), Impulse::NOOP, Impulse::NOOP], switch.__execute_ports());
+        switch.__set_key(Value::String("c".to_owned()));
+        switch.__set_value(Value::Bool(false));
+        assert_eq!(vec![Impulse::SEND(Value::Bool(false)), Impulse::NOOP, Impulse::NOOP, Impulse::NOOP, Impulse::NOOP], switch.__execute_ports());
+        switch.__set_cases(vec![Value::Unit; 5]);
+        switch.__set_key(Value::Unit);
+        switch.__set_value(Value::Unit);
+        assert!(matches!(switch.__execute(), Impulse::ERROR(e) if e.kind == ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn merge() {
+        let mut merge = Merge::__initialize();
+        assert!(merge.__is_ready());
+        assert_eq!(Impulse::NOOP, merge.__execute());
+        merge.__set_b(Value::Int(2));
+        assert_eq!(Impulse::SEND(Value::Int(2)), merge.__execute());
+        merge.__set_a(Value::Int(1));
+        merge.__set_b(Value::Int(2));
+        assert_eq!(Impulse::SEND(Value::Int(1)), merge.__execute());
+        assert_eq!(Impulse::NOOP, merge.__execute()); // the inputs are reset after each execution
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = delta_lib::NodeRegistry::new();
+        crate::__delta_plugin_register(&mut registry);
+        assert_eq!(9, registry.nodes().count());
+        assert_eq!(<Switch as DeltaPorts>::__ports(), registry.get("logic::Switch").unwrap().ports);
+    }
+}